@@ -0,0 +1,43 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use strategy_game::map::{WrapMode, noise_map};
+use strategy_game::erosion::{
+    ThermalErosionParameters, 
+    HydraulicErosionParameters, 
+    thermal_erosion, 
+    hydraulic_erosion
+};
+
+fn erosion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("erosion");
+    group.sample_size(10);
+
+    for map_size_level in 0..5 {
+        let map_size = 2_usize.pow(4 + map_size_level) + 1;
+        let height_map = noise_map(map_size, map_size, 16, 2.0, 2.0, 1.0, false, WrapMode::None, 0);
+
+        let thermal_parameters = ThermalErosionParameters::default();
+        group.bench_with_input(BenchmarkId::new("thermal", map_size_level), &height_map, |b, height_map| {
+            b.iter(|| {
+                let mut eroded_map = height_map.clone();
+                thermal_erosion(&mut eroded_map, &thermal_parameters);
+            })
+        });
+
+        let hydraulic_parameters = HydraulicErosionParameters {
+            seed: Some(0),
+            ..HydraulicErosionParameters::default()
+        };
+        group.bench_with_input(BenchmarkId::new("hydraulic", map_size_level), &height_map, |b, height_map| {
+            b.iter(|| {
+                let mut eroded_map = height_map.clone();
+                hydraulic_erosion(&mut eroded_map, &hydraulic_parameters);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, erosion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,76 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use strategy_game::map::{Map, WrapMode, noise_map};
+use strategy_game::game::Terrain;
+use strategy_game::distance::{EuclideanDistance2D, EuclideanDistanceWHeight2D};
+use strategy_game::constraint::{PositionConstraint, TerrainConstraint};
+use strategy_game::path_planning::{DenseAStar, astar_2d_map, passability_map};
+
+// Cells under this height are impractical water
+const SEA_LEVEL: f64 = 0.3;
+
+fn path_planning_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("path_planning");
+    group.sample_size(10);
+
+    for map_size in [257, 1025].iter().copied() {
+        let height_map = noise_map(map_size, map_size, 8, 2.0, 2.0, 1.0, false, WrapMode::None, 0);
+
+        // Terrain map as built by the game, with water as the only impractical terrain
+        let water = Rc::new(Terrain {name: String::from("Water"), impractical: true, ..Terrain::default()});
+        let land = Rc::new(Terrain {name: String::from("Land"), ..Terrain::default()});
+        let terrain_map = Rc::new(RefCell::new(height_map.map(|h| {
+            if *h < SEA_LEVEL {Rc::downgrade(&water)} else {Rc::downgrade(&land)}
+        })));
+        let constraints = || -> Vec<Box<dyn PositionConstraint>> {
+            vec![Box::new(TerrainConstraint {
+                terrain_map: Rc::downgrade(&terrain_map),
+                impractical_terrains: vec![Rc::downgrade(&water)]
+            })]
+        };
+
+        // Path between the first and the last land cells
+        let passable_map = passability_map(map_size, map_size, WrapMode::None, &constraints());
+        let land_cells: Vec<(usize, usize)> = passable_map.iter_positions().filter(|(_, passable)| **passable).map(|(pos, _)| pos).collect();
+        let start = (land_cells[0].0 as i32, land_cells[0].1 as i32);
+        let goal = (land_cells[land_cells.len() - 1].0 as i32, land_cells[land_cells.len() - 1].1 as i32);
+        let map_dimensions = (map_size as i32, map_size as i32);
+
+        group.bench_function(BenchmarkId::new("hash_astar", map_size), |b| {
+            b.iter(|| {
+                astar_2d_map(
+                    start, 
+                    goal, 
+                    map_dimensions, 
+                    WrapMode::None, 
+                    EuclideanDistanceWHeight2D {height_map: &height_map}, 
+                    EuclideanDistance2D {}, 
+                    constraints(), 
+                    vec![]
+                )
+            })
+        });
+
+        let mut dense_astar = DenseAStar::new(map_size, map_size);
+        group.bench_function(BenchmarkId::new("dense_astar", map_size), |b| {
+            b.iter(|| {
+                dense_astar.search(start, goal, &passable_map, &EuclideanDistanceWHeight2D {height_map: &height_map}, &EuclideanDistance2D {})
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("dense_astar_with_baking", map_size), |b| {
+            b.iter(|| {
+                let passable_map: Map<bool> = passability_map(map_size, map_size, WrapMode::None, &constraints());
+                dense_astar.search(start, goal, &passable_map, &EuclideanDistanceWHeight2D {height_map: &height_map}, &EuclideanDistance2D {})
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, path_planning_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use crate::player::Unit;
+
+// Duration in seconds of a unit move between two neighbour cells and of the feedback of an attack
+const MOVE_STEP_DURATION: f64 = 0.12;
+const DAMAGE_DURATION: f64 = 0.8;
+
+// Animations are played faster when there are many of them queued, so that the queue is never longer than this
+const MAX_QUEUE_DURATION: f64 = 2.0;
+
+// Visual change played after the game state it shows has already been updated
+#[derive(Debug, Clone)]
+pub enum Animation {
+    // Unit going from a cell to a neighbour one
+    Move {unit: Weak<RefCell<Unit>>, from: (usize, usize), to: (usize, usize)},
+
+    // Damage dealt to the unit or building on a cell, shown with a flash and a floating number
+    Damage {position: (usize, usize), damage: f64}
+}
+
+impl Animation {
+    pub fn duration(&self) -> f64 {
+        match self {
+            Animation::Move {..} => MOVE_STEP_DURATION,
+            Animation::Damage {..} => DAMAGE_DURATION
+        }
+    }
+}
+
+/// Animations played one after the other, the first one being the one playing
+#[derive(Debug, Default)]
+pub struct AnimationQueue {
+    pub queue: VecDeque<Animation>,
+
+    // Time spent playing the first animation
+    pub elapsed: f64
+}
+
+impl AnimationQueue {
+    pub fn push(&mut self, animation: Animation) {
+        self.queue.push_back(animation);
+    }
+
+    /// Drop all animations, visuals jumping to the game state
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.elapsed = 0.0;
+    }
+
+    /// Advance the animations by a time step, dropping the finished ones
+    pub fn update(&mut self, dt: f64) {
+        let queue_duration: f64 = self.queue.iter().map(Animation::duration).sum::<f64>() - self.elapsed;
+        let speed = (queue_duration / MAX_QUEUE_DURATION).max(1.0);
+
+        self.elapsed += dt * speed;
+        while let Some(animation) = self.queue.front() {
+            if self.elapsed < animation.duration() {
+                break;
+            }
+            self.elapsed -= animation.duration();
+            self.queue.pop_front();
+        }
+        if self.queue.is_empty() {
+            self.elapsed = 0.0;
+        }
+    }
+
+    /// Animation playing and its progress between 0 and 1
+    pub fn current(&self) -> Option<(&Animation, f64)> {
+        self.queue.front().map(|animation| (animation, (self.elapsed / animation.duration()).min(1.0)))
+    }
+
+    /// First move of a unit still to be shown and its progress (0 if it is not playing yet), the unit being drawn
+    /// along it instead of at its position
+    pub fn unit_move(&self, unit: &Rc<RefCell<Unit>>) -> Option<(&Animation, f64)> {
+        self.queue.iter().enumerate().find_map(|(index, animation)| match animation {
+            Animation::Move {unit: moved_unit, ..} if moved_unit.as_ptr() == Rc::as_ptr(unit) => {
+                let progress = if index == 0 {(self.elapsed / animation.duration()).min(1.0)} else {0.0};
+                Some((animation, progress))
+            },
+            _ => None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Unit of the first player at a position
+    fn unit(position: (usize, usize)) -> Rc<RefCell<Unit>> {
+        Rc::new(RefCell::new(Unit {player: 0, position, damage: 1.0, health: 1.0, speed: 1.0, remaining_moves: 1.0, goal: None}))
+    }
+
+    // Move of a unit between two cells
+    fn move_animation(unit: &Rc<RefCell<Unit>>, from: (usize, usize), to: (usize, usize)) -> Animation {
+        Animation::Move {unit: Rc::downgrade(unit), from, to}
+    }
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!((value - expected).abs() < 1e-9, "{} is not {}", value, expected);
+    }
+
+    #[test]
+    fn elapsed_time_carries_over_finished_animations() {
+        let mut animations = AnimationQueue::default();
+        animations.push(Animation::Damage {position: (0, 0), damage: 1.0});
+        animations.push(Animation::Damage {position: (1, 0), damage: 2.0});
+
+        animations.update(DAMAGE_DURATION / 2.0);
+        let (_, progress) = animations.current().unwrap();
+        assert_close(progress, 0.5);
+
+        // The time left over by the first animation is spent on the second one
+        animations.update(DAMAGE_DURATION * 0.75);
+        assert_eq!(animations.queue.len(), 1);
+        assert_close(animations.elapsed, DAMAGE_DURATION / 4.0);
+
+        animations.update(DAMAGE_DURATION);
+        assert!(animations.current().is_none());
+        assert_eq!(animations.elapsed, 0.0);
+    }
+
+    #[test]
+    fn long_queues_are_sped_up() {
+        let unit = unit((0, 0));
+        let steps = 2 * (MAX_QUEUE_DURATION / MOVE_STEP_DURATION).ceil() as usize;
+        let mut animations = AnimationQueue::default();
+        for step in 0..steps {
+            animations.push(move_animation(&unit, (0, step), (0, step + 1)));
+        }
+
+        // A queue about twice as long as the maximum is played about twice as fast, so it lasts the maximum
+        let speed = steps as f64 * MOVE_STEP_DURATION / MAX_QUEUE_DURATION;
+        assert!(speed >= 2.0);
+        animations.update(MOVE_STEP_DURATION);
+        assert_eq!(animations.queue.len(), steps - speed.floor() as usize);
+        assert_close(animations.elapsed, MOVE_STEP_DURATION * speed.fract());
+
+        // Short queues are played at normal speed
+        let mut animations = AnimationQueue::default();
+        animations.push(move_animation(&unit, (0, 0), (0, 1)));
+        animations.update(MOVE_STEP_DURATION / 4.0);
+        assert_close(animations.elapsed, MOVE_STEP_DURATION / 4.0);
+    }
+
+    #[test]
+    fn unit_moves_progress_only_when_playing() {
+        let first_unit = unit((0, 0));
+        let second_unit = unit((5, 5));
+        let mut animations = AnimationQueue::default();
+        animations.push(move_animation(&first_unit, (0, 0), (0, 1)));
+        animations.push(move_animation(&second_unit, (5, 5), (5, 6)));
+        animations.push(move_animation(&first_unit, (0, 1), (0, 2)));
+        animations.update(MOVE_STEP_DURATION / 2.0);
+
+        // First move of a unit is the one returned
+        match animations.unit_move(&first_unit) {
+            Some((Animation::Move {from, ..}, progress)) => {
+                assert_eq!(*from, (0, 0));
+                assert_close(progress, 0.5);
+            },
+            _ => panic!("first unit move not found")
+        }
+
+        // Moves waiting in the queue have not started yet
+        match animations.unit_move(&second_unit) {
+            Some((Animation::Move {from, ..}, progress)) => {
+                assert_eq!(*from, (5, 5));
+                assert_eq!(progress, 0.0);
+            },
+            _ => panic!("second unit move not found")
+        }
+        assert!(animations.unit_move(&unit((0, 0))).is_none());
+
+        animations.clear();
+        assert!(animations.unit_move(&first_unit).is_none());
+        assert_eq!(animations.elapsed, 0.0);
+    }
+}
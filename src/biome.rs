@@ -0,0 +1,268 @@
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::map::{Map, noise_map};
+use crate::game::Terrain;
+
+// Whittaker-style lookup table selecting a terrain from temperature and moisture
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BiomeTable {
+    // Height interval of cells whose terrain is chosen by the table
+    pub height_interval: (f64, f64),
+
+    // Ascending thresholds splitting temperature (rows) and moisture (columns) into bands
+    pub temperature_thresholds: Vec<f64>,
+    pub moisture_thresholds: Vec<f64>,
+
+    // Terrain names, one row per temperature band (cold to hot), one column per moisture band (dry to wet)
+    pub table: Vec<Vec<String>>,
+
+    // Terrain indices resolved from names once terrain data is loaded
+    #[serde(skip)]
+    pub terrain_indices: Vec<Vec<usize>>
+}
+
+impl BiomeTable {
+    fn band(thresholds: &[f64], value: f64) -> usize {
+        thresholds.iter().take_while(|threshold| value >= **threshold).count()
+    }
+
+    /// Index of the terrain matching a temperature and a moisture (both in [0, 1])
+    pub fn terrain_index(&self, temperature: f64, moisture: f64) -> usize {
+        let row = BiomeTable::band(&self.temperature_thresholds, temperature);
+        let column = BiomeTable::band(&self.moisture_thresholds, moisture);
+        self.terrain_indices[row][column]
+    }
+
+    pub fn contains_height(&self, height: f64) -> bool {
+        height >= self.height_interval.0 && height < self.height_interval.1
+    }
+}
+
+// Content of the terrain data file
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TerrainData {
+    pub terrains: Vec<Terrain>,
+    pub biomes: BiomeTable
+}
+
+/// Load terrains and biome table from a RON terrain data file
+pub fn load_terrain_data(path: &str) -> Result<TerrainData, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|error| format!("Cannot read terrain data file '{}': {}", path, error))?;
+    let mut terrain_data: TerrainData = ron::de::from_str(&content)
+        .map_err(|error| format!("Cannot parse terrain data file '{}': {}", path, error))?;
+
+    // Check biome table dimensions against thresholds
+    let biomes = &terrain_data.biomes;
+    if biomes.table.len() != biomes.temperature_thresholds.len() + 1 {
+        return Err(format!(
+            "Biome table must have {} rows (one per temperature band), found {}",
+            biomes.temperature_thresholds.len() + 1, biomes.table.len()
+        ));
+    }
+
+    // Resolve terrain names of the biome table
+    let mut terrain_indices = Vec::new();
+    for row in biomes.table.iter() {
+        if row.len() != biomes.moisture_thresholds.len() + 1 {
+            return Err(format!(
+                "Biome table rows must have {} columns (one per moisture band), found {}",
+                biomes.moisture_thresholds.len() + 1, row.len()
+            ));
+        }
+
+        let mut row_indices = Vec::new();
+        for name in row.iter() {
+            match terrain_data.terrains.iter().position(|terrain| &terrain.name == name) {
+                Some(index) => row_indices.push(index),
+                None => return Err(format!("Biome table refers to unknown terrain '{}'", name))
+            }
+        }
+        terrain_indices.push(row_indices);
+    }
+    terrain_data.biomes.terrain_indices = terrain_indices;
+
+    Ok(terrain_data)
+}
+
+// Structure holding parameters of temperature and moisture generation
+#[derive(Debug, Clone, Copy)]
+pub struct ClimateParameters {
+    // Heights under which cells are considered as water or above which they block clouds
+    pub sea_level: f64,
+    pub mountain_level: f64,
+
+    // Weight of latitude gradient against noise in temperature
+    pub latitude_weight: f64,
+
+    // Temperature lost per unit of height above sea level
+    pub altitude_lapse: f64,
+
+    // Fraction of humidity kept by winds each time they cross a mountain cell
+    pub rain_shadow: f64,
+
+    // Fraction of humidity recovered by winds each time they cross a water cell
+    pub evaporation: f64,
+
+    // Fraction of humidity lost by winds each time they cross a land cell
+    pub rainfall: f64,
+
+    // Weight of wind humidity against noise in moisture
+    pub wind_weight: f64
+}
+
+impl Default for ClimateParameters {
+    fn default() -> Self {
+        ClimateParameters {
+            sea_level: 0.275,
+            mountain_level: 0.7,
+            latitude_weight: 0.7,
+            altitude_lapse: 0.4,
+            rain_shadow: 0.7,
+            evaporation: 0.2,
+            rainfall: 0.05,
+            wind_weight: 0.6
+        }
+    }
+}
+
+/// Temperature map: warm around the equator (middle rows), cold at the poles and on heights
+pub fn temperature_map(height_map: &Map<f64>, parameters: &ClimateParameters, seed: u64) -> Map<f64> {
+    let mut temperature_map = noise_map(height_map.width, height_map.height, 8, 2.0, 2.0, 1.0, false, height_map.wrap, seed);
+
+    let equator = (height_map.height - 1) as f64 / 2.0;
+    for i in 0..height_map.height {
+        let latitude = if equator > 0.0 {(i as f64 - equator).abs() / equator} else {0.0};
+        for j in 0..height_map.width {
+            let altitude = (height_map[(i, j)] - parameters.sea_level).max(0.0);
+            let temperature =
+                (1.0 - latitude) * parameters.latitude_weight +
+                temperature_map[(i, j)] * (1.0 - parameters.latitude_weight) -
+                altitude * parameters.altitude_lapse;
+            temperature_map[(i, j)] = temperature.clamp(0.0, 1.0);
+        }
+    }
+
+    temperature_map
+}
+
+/// Moisture map: noise blended with the humidity carried by west winds, which grows over water
+/// and drops behind mountains (rain shadow)
+pub fn moisture_map(height_map: &Map<f64>, parameters: &ClimateParameters, seed: u64) -> Map<f64> {
+    let mut moisture_map = noise_map(height_map.width, height_map.height, 8, 2.0, 2.0, 1.0, false, height_map.wrap, seed);
+
+    // On horizontally wrapped maps, winds go around the map once before humidity is recorded
+    let laps = if height_map.wrap.wraps_horizontally() {2} else {1};
+
+    for i in 0..height_map.height {
+        // Winds blow from west to east
+        let mut humidity = 1.0;
+        for lap_j in 0..(laps * height_map.width) {
+            let j = lap_j % height_map.width;
+            let height = height_map[(i, j)];
+            if height < parameters.sea_level {
+                humidity += (1.0 - humidity) * parameters.evaporation;
+            }
+            else if height >= parameters.mountain_level {
+                humidity *= parameters.rain_shadow;
+            }
+            else {
+                humidity *= 1.0 - parameters.rainfall;
+            }
+
+            if lap_j + height_map.width < laps * height_map.width {
+                continue;
+            }
+
+            moisture_map[(i, j)] = humidity * parameters.wind_weight + moisture_map[(i, j)] * (1.0 - parameters.wind_weight);
+        }
+    }
+
+    moisture_map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Load terrain data from a temporary file with sand and grass terrains and this biome table
+    fn load_biomes(name: &str, biomes: &str) -> Result<TerrainData, String> {
+        let content = format!(
+            "(terrains: [(name: \"Sand\", color: (1.0, 1.0, 0.0, 1.0)), (name: \"Grass\", color: (0.0, 1.0, 0.0, 1.0))], biomes: {})",
+            biomes
+        );
+        let path = std::env::temp_dir().join(format!("strategy_game_terrains_{}_{}.ron", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        let terrain_data = load_terrain_data(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        terrain_data
+    }
+
+    // Name of the terrain chosen by the biome table
+    fn biome(terrain_data: &TerrainData, temperature: f64, moisture: f64) -> &str {
+        &terrain_data.terrains[terrain_data.biomes.terrain_index(temperature, moisture)].name
+    }
+
+    #[test]
+    fn biome_table_maps_temperature_and_moisture_to_terrains() {
+        let terrain_data = load_terrain_data(concat!(env!("CARGO_MANIFEST_DIR"), "/data/terrains.ron")).unwrap();
+        assert_eq!(biome(&terrain_data, 0.1, 0.1), "Tundra");
+        assert_eq!(biome(&terrain_data, 0.1, 0.9), "Taiga");
+        assert_eq!(biome(&terrain_data, 0.6, 0.6), "Forest");
+        assert_eq!(biome(&terrain_data, 0.9, 0.1), "Desert");
+        assert_eq!(biome(&terrain_data, 0.9, 0.9), "Swamp");
+
+        // Thresholds belong to the band above them
+        assert_eq!(biome(&terrain_data, 0.2, 0.0), "Grass");
+        assert_eq!(biome(&terrain_data, 0.8, 0.5), "Rainforest");
+    }
+
+    #[test]
+    fn invalid_biome_tables_are_rejected() {
+        let biomes = "(height_interval: (0.3, 0.7), temperature_thresholds: [0.5], moisture_thresholds: [], table: [[\"Sand\"], [\"Grass\"]])";
+        assert!(load_biomes("valid", biomes).is_ok());
+
+        let error = load_biomes("unknown", &biomes.replace("Grass\"]", "Lava\"]")).unwrap_err();
+        assert!(error.contains("unknown terrain 'Lava'"));
+        let error = load_biomes("empty", "(height_interval: (0.3, 0.7), temperature_thresholds: [], moisture_thresholds: [], table: [])").unwrap_err();
+        assert!(error.contains("must have 1 rows"));
+        let error = load_biomes("columns", &biomes.replace("[\"Sand\"]", "[\"Sand\", \"Grass\"]")).unwrap_err();
+        assert!(error.contains("must have 1 columns"));
+        assert!(load_biomes("syntax", "(table: [[\"Sand\"]]").unwrap_err().contains("Cannot parse"));
+    }
+
+    #[test]
+    fn mountains_cast_a_rain_shadow() {
+        // Sea in the west, then land with a mountain ridge in the middle
+        let mut height_map = Map::new(24, 4, 0.5);
+        for i in 0..4 {
+            for j in 0..6 {
+                height_map[(i, j)] = 0.1;
+            }
+            for j in 12..15 {
+                height_map[(i, j)] = 0.8;
+            }
+        }
+
+        // Moisture only given by winds
+        let parameters = ClimateParameters {wind_weight: 1.0, ..ClimateParameters::default()};
+        let moisture_map = moisture_map(&height_map, &parameters, 3);
+        for i in 0..4 {
+            assert!(moisture_map[(i, 15)] < moisture_map[(i, 11)] * parameters.rain_shadow);
+            assert!(moisture_map[(i, 11)] < moisture_map[(i, 6)]);
+        }
+    }
+
+    #[test]
+    fn temperature_drops_with_latitude_and_altitude() {
+        let mut height_map = Map::new(4, 9, 0.3);
+        height_map[(4, 3)] = 1.0;
+        let parameters = ClimateParameters {latitude_weight: 1.0, ..ClimateParameters::default()};
+        let temperature_map = temperature_map(&height_map, &parameters, 3);
+        assert!(temperature_map[(0, 0)] < temperature_map[(2, 0)]);
+        assert!(temperature_map[(2, 0)] < temperature_map[(4, 0)]);
+        assert!(temperature_map[(4, 3)] < temperature_map[(4, 0)]);
+    }
+}
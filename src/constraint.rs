@@ -32,7 +32,7 @@ impl PositionConstraint for TerrainConstraint {
             }
         }
         
-        return true;
+        true
     }
 }
 
@@ -48,7 +48,7 @@ impl PositionConstraint for UnitConstraint {
             return unit_map.borrow()[pos].upgrade().is_none();
         }
 
-        return true;
+        true
     }
 }
 
@@ -64,6 +64,6 @@ impl PositionConstraint for BuildingConstraint {
             return building_map.borrow()[pos].upgrade().is_none();
         }
 
-        return true;
+        true
     }
 }
\ No newline at end of file
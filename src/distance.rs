@@ -1,4 +1,4 @@
-use crate::map::Map;
+use crate::map::{Map, WrapMode};
 
 pub trait Distance2D {
     fn evaluate(&self, pos1: (f64, f64), pos2: (f64, f64)) -> f64;
@@ -32,15 +32,30 @@ pub struct EuclideanDistanceWHeight2D<'d> {
     pub height_map: &'d Map<f64>
 }
 
-impl<'d> Distance2D for EuclideanDistanceWHeight2D<'_> {
+impl Distance2D for EuclideanDistanceWHeight2D<'_> {
     fn evaluate(&self, pos1: (f64, f64), pos2: (f64, f64)) -> f64 {
         // Get associated height for the two position
         let ipos1 = (pos1.0 as i32, pos1.1 as i32);
         let ipos2 = (pos2.0 as i32, pos2.1 as i32);
 
-        let height1 = self.height_map[ipos1] * 2.0;
-        let height2 = self.height_map[ipos2] * 2.0;
+        // Positions may be across edges of wrapped maps (see WrappedDistance2D)
+        let height1 = self.height_map.get(ipos1).expect("Position is out of height map") * 2.0;
+        let height2 = self.height_map.get(ipos2).expect("Position is out of height map") * 2.0;
 
         ((pos2.0 - pos1.0).powi(2) + (pos2.1 - pos1.1).powi(2) + (height2 - height1).powi(2)).sqrt()
     }
-}
\ No newline at end of file
+}
+
+/// Distance evaluated between a position and the closest copy of the other one on a wrapped map
+pub struct WrappedDistance2D<D: Distance2D> {
+    pub distance: D,
+    pub map_size: (f64, f64),
+    pub wrap_mode: WrapMode
+}
+
+impl<D: Distance2D> Distance2D for WrappedDistance2D<D> {
+    fn evaluate(&self, pos1: (f64, f64), pos2: (f64, f64)) -> f64 {
+        let (di, dj) = self.wrap_mode.delta(pos1, pos2, self.map_size);
+        self.distance.evaluate(pos1, (pos1.0 + di, pos1.1 + dj))
+    }
+}
@@ -0,0 +1,322 @@
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use crate::map::{Map, Neighbourhood};
+
+// Structure holding thermal erosion parameters
+#[derive(Debug, Clone, Copy)]
+pub struct ThermalErosionParameters {
+    // Number of erosion passes over the whole map
+    pub iterations: usize,
+
+    // Height difference above which material starts sliding to a lower neighbour
+    pub talus: f64,
+
+    // Fraction of the exceeding material moved at each pass
+    pub strength: f64
+}
+
+impl Default for ThermalErosionParameters {
+    fn default() -> Self {
+        ThermalErosionParameters {
+            iterations: 8,
+            talus: 0.02,
+            strength: 0.5
+        }
+    }
+}
+
+// Structure holding droplet-based hydraulic erosion parameters
+#[derive(Debug, Clone, Copy)]
+pub struct HydraulicErosionParameters {
+    // Number of simulated droplets per map cell
+    pub droplets_per_cell: f64,
+
+    // Maximum number of steps a droplet can do before vanishing
+    pub max_lifetime: usize,
+
+    // Seed of droplet spawning positions (random seed if None)
+    pub seed: Option<u64>,
+
+    // How much a droplet keeps its previous direction (0: follows the slope, 1: never turns)
+    pub inertia: f64,
+
+    // Sediment capacity factor and minimal capacity of a droplet
+    pub capacity: f64,
+    pub min_capacity: f64,
+
+    // Fraction of the sediment surplus deposited and of the capacity deficit eroded at each step
+    pub deposit_speed: f64,
+    pub erode_speed: f64,
+
+    // Fraction of water evaporated at each step
+    pub evaporate_speed: f64,
+
+    pub gravity: f64,
+    pub initial_water: f64,
+    pub initial_speed: f64
+}
+
+impl Default for HydraulicErosionParameters {
+    fn default() -> Self {
+        HydraulicErosionParameters {
+            droplets_per_cell: 0.5,
+            max_lifetime: 30,
+            seed: None,
+            inertia: 0.05,
+            capacity: 4.0,
+            min_capacity: 0.01,
+            deposit_speed: 0.3,
+            erode_speed: 0.3,
+            evaporate_speed: 0.01,
+            gravity: 4.0,
+            initial_water: 1.0,
+            initial_speed: 1.0
+        }
+    }
+}
+
+/// Thermal erosion simulation: material slides from a cell to its lower neighbours
+/// as long as the slope between them exceeds the talus threshold
+///
+/// # Arguments
+///
+/// * `height_map` - Height map eroded in place
+/// * `parameters` - Number of passes, talus threshold and strength of the erosion
+///
+pub fn thermal_erosion(height_map: &mut Map<f64>, parameters: &ThermalErosionParameters) {
+    // Height variations computed during one pass, applied at the end of it
+    let mut delta_map: Map<f64> = Map::new(height_map.width, height_map.height, 0.0);
+
+    for _ in 0..parameters.iterations {
+        for (pos, &h) in height_map.iter_positions() {
+            // Find the total and maximal height difference with lower neighbours
+            let mut total_difference = 0.0;
+            let mut max_difference = 0.0_f64;
+            for neighbour in height_map.neighbours(pos, Neighbourhood::Moore) {
+                let difference = h - height_map[neighbour];
+                if difference > parameters.talus {
+                    total_difference += difference;
+                    max_difference = max_difference.max(difference);
+                }
+            }
+
+            if total_difference <= 0.0 {
+                continue;
+            }
+
+            // Distribute moved material proportionally to each height difference
+            let moved = parameters.strength * (max_difference - parameters.talus);
+            delta_map[pos] -= moved;
+            for neighbour in height_map.neighbours(pos, Neighbourhood::Moore) {
+                let difference = h - height_map[neighbour];
+                if difference > parameters.talus {
+                    delta_map[neighbour] += moved * difference / total_difference;
+                }
+            }
+        }
+
+        // Apply height variations and reset them for the next pass
+        *height_map = height_map.zip_with(&delta_map, |h, delta| h + delta);
+        delta_map.fill(0.0);
+    }
+}
+
+/// Floating position brought back into the map along wrapped axes, None if the cell containing it has a corner out
+/// of the map
+fn wrap_droplet_position(height_map: &Map<f64>, pos: (f64, f64)) -> Option<(f64, f64)> {
+    let (ci, cj) = (pos.0.floor(), pos.1.floor());
+    height_map.wrap_position((ci as i32 + 1, cj as i32 + 1))?;
+    let (i, j) = height_map.wrap_position((ci as i32, cj as i32))?;
+    Some((i as f64 + pos.0 - ci, j as f64 + pos.1 - cj))
+}
+
+/// Four corners of the cell containing a floating position (across edges on wrapped maps) and the position in it
+fn cell_corners(height_map: &Map<f64>, pos: (f64, f64)) -> ([(usize, usize); 4], (f64, f64)) {
+    let (ci, cj) = (pos.0 as i32, pos.1 as i32);
+    let corner = |di: i32, dj: i32| {
+        let (i, j) = height_map.wrap_position((ci + di, cj + dj)).unwrap();
+        (i as usize, j as usize)
+    };
+    ([corner(0, 0), corner(0, 1), corner(1, 0), corner(1, 1)], (pos.0 - ci as f64, pos.1 - cj as f64))
+}
+
+/// Bilinear interpolation of height and gradient at a floating position of the map
+fn height_and_gradient(height_map: &Map<f64>, pos: (f64, f64)) -> (f64, (f64, f64)) {
+    let ([c00, c01, c10, c11], (u, v)) = cell_corners(height_map, pos);
+
+    // Heights of the four corners of the cell
+    let h00 = height_map[c00];
+    let h01 = height_map[c01];
+    let h10 = height_map[c10];
+    let h11 = height_map[c11];
+
+    let gradient_i = (h10 - h00) * (1.0 - v) + (h11 - h01) * v;
+    let gradient_j = (h01 - h00) * (1.0 - u) + (h11 - h10) * u;
+    let h = h00 * (1.0 - u) * (1.0 - v) + h01 * (1.0 - u) * v + h10 * u * (1.0 - v) + h11 * u * v;
+
+    (h, (gradient_i, gradient_j))
+}
+
+/// Add an amount of material to the four corners of the cell containing a floating position
+fn deposit_bilinear(height_map: &mut Map<f64>, pos: (f64, f64), amount: f64) {
+    let ([c00, c01, c10, c11], (u, v)) = cell_corners(height_map, pos);
+
+    height_map[c00] += amount * (1.0 - u) * (1.0 - v);
+    height_map[c01] += amount * (1.0 - u) * v;
+    height_map[c10] += amount * u * (1.0 - v);
+    height_map[c11] += amount * u * v;
+}
+
+/// Droplet-based hydraulic erosion simulation: droplets spawn at random positions, flow down
+/// the slope, erode material where they speed up and deposit it where they slow down
+///
+/// # Arguments
+///
+/// * `height_map` - Height map eroded in place
+/// * `parameters` - Droplet physical constants, number of droplets and random seed
+///
+pub fn hydraulic_erosion(height_map: &mut Map<f64>, parameters: &HydraulicErosionParameters) {
+    // Droplets need a cell with four corners to interpolate height
+    if height_map.width < 2 || height_map.height < 2 {
+        return;
+    }
+
+    // Initialize random number generator with passed seed if any
+    let mut rng = match parameters.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy()
+    };
+
+    // Droplets spawn in cells with four corners in the map, all of them along wrapped axes
+    let max_i = (height_map.height - if height_map.wrap.wraps_vertically() {0} else {1}) as f64;
+    let max_j = (height_map.width - if height_map.wrap.wraps_horizontally() {0} else {1}) as f64;
+    let droplets_num = (parameters.droplets_per_cell * (height_map.width * height_map.height) as f64) as usize;
+
+    for _ in 0..droplets_num {
+        let mut pos = (rng.gen_range(0.0..max_i), rng.gen_range(0.0..max_j));
+        let mut direction = (0.0, 0.0);
+        let mut speed = parameters.initial_speed;
+        let mut water = parameters.initial_water;
+        let mut sediment = 0.0;
+
+        for _ in 0..parameters.max_lifetime {
+            let (h, gradient) = height_and_gradient(height_map, pos);
+
+            // Update droplet direction by mixing previous direction and slope
+            direction = (
+                direction.0 * parameters.inertia - gradient.0 * (1.0 - parameters.inertia),
+                direction.1 * parameters.inertia - gradient.1 * (1.0 - parameters.inertia)
+            );
+            let norm = (direction.0 * direction.0 + direction.1 * direction.1).sqrt();
+            if norm <= f64::EPSILON {
+                break;
+            }
+            direction = (direction.0 / norm, direction.1 / norm);
+
+            // Move droplet by one cell, across edges on wrapped maps, and stop it if it leaves the map
+            let new_pos = match wrap_droplet_position(height_map, (pos.0 + direction.0, pos.1 + direction.1)) {
+                Some(new_pos) => new_pos,
+                None => break
+            };
+
+            let (new_h, _) = height_and_gradient(height_map, new_pos);
+            let delta_h = new_h - h;
+
+            // Sediment capacity grows with slope, speed and water quantity
+            let capacity = (-delta_h * speed * water * parameters.capacity).max(parameters.min_capacity);
+
+            if sediment > capacity || delta_h > 0.0 {
+                // Going uphill fills the pit behind, otherwise deposit surplus
+                let amount = if delta_h > 0.0 {
+                    delta_h.min(sediment)
+                }
+                else {
+                    (sediment - capacity) * parameters.deposit_speed
+                };
+                sediment -= amount;
+                deposit_bilinear(height_map, pos, amount);
+            }
+            else {
+                // Never erode more than the height difference to avoid digging holes
+                let amount = ((capacity - sediment) * parameters.erode_speed).min(-delta_h);
+                sediment += amount;
+                deposit_bilinear(height_map, pos, -amount);
+            }
+
+            speed = (speed * speed - delta_h * parameters.gravity).max(0.0).sqrt();
+            water *= 1.0 - parameters.evaporate_speed;
+            pos = new_pos;
+        }
+
+        // Vanishing droplet drops the sediment it still carries
+        deposit_bilinear(height_map, pos, sediment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{noise_map, WrapMode};
+
+    // Normalized noise height map
+    fn test_height_map(wrap: WrapMode) -> Map<f64> {
+        let mut height_map = noise_map(32, 24, 4, 2.0, 0.5, 1.0, false, wrap, 5);
+        height_map.normalize();
+        height_map
+    }
+
+    // Largest height difference between a cell and one of its neighbours
+    fn max_slope(height_map: &Map<f64>) -> f64 {
+        height_map.iter_positions()
+            .flat_map(|(pos, h)| height_map.neighbours(pos, Neighbourhood::Moore).map(move |neighbour| (*h, neighbour)))
+            .map(|(h, neighbour)| (h - height_map[neighbour]).abs())
+            .fold(0.0, f64::max)
+    }
+
+    fn total_height(height_map: &Map<f64>) -> f64 {
+        height_map.map.iter().sum()
+    }
+
+    #[test]
+    fn thermal_erosion_lowers_steepest_slope() {
+        let mut height_map = test_height_map(WrapMode::None);
+        let slope = max_slope(&height_map);
+        let total = total_height(&height_map);
+        thermal_erosion(&mut height_map, &ThermalErosionParameters::default());
+        assert!(max_slope(&height_map) < slope);
+
+        // Material only slides from a cell to another one
+        assert!((total_height(&height_map) - total).abs() < 1e-9);
+    }
+
+    #[test]
+    fn thermal_erosion_slides_across_wrapped_edges() {
+        let mut height_map = Map::new(8, 8, 0.0);
+        height_map.wrap = WrapMode::Full;
+        height_map[(0, 0)] = 1.0;
+        thermal_erosion(&mut height_map, &ThermalErosionParameters {iterations: 1, ..ThermalErosionParameters::default()});
+        assert!(height_map[(7, 7)] > 0.0);
+        assert!(height_map[(0, 7)] > 0.0);
+    }
+
+    #[test]
+    fn hydraulic_erosion_keeps_material_and_is_deterministic() {
+        for wrap in [WrapMode::None, WrapMode::Horizontal, WrapMode::Full] {
+            let height_map = test_height_map(wrap);
+            let parameters = HydraulicErosionParameters {seed: Some(11), ..HydraulicErosionParameters::default()};
+            let mut eroded_map = height_map.clone();
+            hydraulic_erosion(&mut eroded_map, &parameters);
+            assert!(eroded_map.map != height_map.map);
+
+            // Material is only moved by droplets, vanishing ones dropping their sediment
+            let total = total_height(&height_map);
+            let difference = (total_height(&eroded_map) - total).abs();
+            assert!(difference < 1e-6 * total, "{:?}: {} material lost out of {}", wrap, difference, total);
+
+            let mut other_eroded_map = height_map.clone();
+            hydraulic_erosion(&mut other_eroded_map, &parameters);
+            assert!(eroded_map.map == other_eroded_map.map);
+        }
+    }
+}
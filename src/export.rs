@@ -0,0 +1,290 @@
+use std::path::Path;
+use std::rc::Weak;
+
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+
+use crate::map::{Map, Neighbourhood};
+use crate::game::{Game, Terrain};
+use crate::player::Player;
+
+// Pixel color of an unowned cell in territory images
+const NO_TERRITORY_COLOR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+// Opacity of player colors painted over terrain in snapshots and minimaps
+const TERRITORY_TINT_ALPHA: f32 = 0.25;
+const MINIMAP_TERRITORY_TINT_ALPHA: f32 = 0.5;
+
+// Brightness of the terrain of cells out of sight
+const HIDDEN_CELL_BRIGHTNESS: f32 = 0.3;
+
+/// Pixel of a color whose channels are between 0 and 1
+pub fn to_rgba(color: [f32; 4]) -> Rgba<u8> {
+    let channel = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgba([channel(color[0]), channel(color[1]), channel(color[2]), channel(color[3])])
+}
+
+// Blend a color over a pixel, the resulting pixel is opaque
+fn blend(pixel: &mut Rgba<u8>, color: [f32; 4], alpha: f32) {
+    let over = to_rgba(color);
+    for c in 0..3 {
+        pixel[c] = (pixel[c] as f32 * (1.0 - alpha) + over[c] as f32 * alpha).round() as u8;
+    }
+    pixel[3] = 255;
+}
+
+fn save(image: DynamicImage, path: &Path) -> Result<(), String> {
+    image.save(path).map_err(|error| format!("Cannot write image '{}': {}", path.display(), error))
+}
+
+/// Grayscale image of a height map, one pixel per cell (heights are clamped into [0, 1])
+pub fn height_map_image(height_map: &Map<f64>) -> GrayImage {
+    GrayImage::from_fn(height_map.width as u32, height_map.height as u32, |x, y| {
+        Luma([(height_map[(y as usize, x as usize)].clamp(0.0, 1.0) * 255.0).round() as u8])
+    })
+}
+
+/// Image of a terrain map with terrain colors, one pixel per cell
+pub fn terrain_map_image(terrain_map: &Map<Weak<Terrain>>) -> RgbaImage {
+    RgbaImage::from_fn(terrain_map.width as u32, terrain_map.height as u32, |x, y| {
+        match terrain_map[(y as usize, x as usize)].upgrade() {
+            Some(terrain) => to_rgba(terrain.color),
+            None => to_rgba(Terrain::default().color)
+        }
+    })
+}
+
+/// Pixel of a territory image: principal color of the owner (transparent if unowned)
+pub fn territory_pixel(owner: usize, players: &[Player]) -> Rgba<u8> {
+    match players.get(owner) {
+        Some(player) => to_rgba(player.principal_color),
+        None => NO_TERRITORY_COLOR
+    }
+}
+
+/// Image of a territory map with player principal colors, one pixel per cell (transparent if unowned)
+pub fn territory_map_image(territory_map: &Map<usize>, players: &[Player]) -> RgbaImage {
+    RgbaImage::from_fn(territory_map.width as u32, territory_map.height as u32, |x, y| {
+        territory_pixel(territory_map[(y as usize, x as usize)], players)
+    })
+}
+
+/// Opaque pixel of a cell out of sight: its terrain color darkened
+pub fn hidden_cell_pixel(terrain_color: [f32; 4]) -> Rgba<u8> {
+    let mut pixel = to_rgba([
+        terrain_color[0] * HIDDEN_CELL_BRIGHTNESS,
+        terrain_color[1] * HIDDEN_CELL_BRIGHTNESS,
+        terrain_color[2] * HIDDEN_CELL_BRIGHTNESS,
+        1.0
+    ]);
+    pixel[3] = 255;
+    pixel
+}
+
+/// Image of a terrain map tinted with territory owner colors, one pixel per cell (used by the minimap)
+///
+/// # Arguments
+///
+/// * `terrain_map` - Terrain of each cell
+/// * `territory_map` - Owner of each cell
+/// * `players` - Players whose colors tint their territory
+/// * `visibility_map` - Cells in sight, the other ones being darkened without territory (all cells are if None)
+///
+pub fn minimap_image(terrain_map: &Map<Weak<Terrain>>, territory_map: &Map<usize>, players: &[Player], visibility_map: Option<&Map<bool>>) -> RgbaImage {
+    RgbaImage::from_fn(terrain_map.width as u32, terrain_map.height as u32, |x, y| {
+        let position = (y as usize, x as usize);
+        let terrain_color = terrain_map[position].upgrade().map_or(Terrain::default().color, |terrain| terrain.color);
+        if visibility_map.is_some_and(|visibility_map| !visibility_map[position]) {
+            return hidden_cell_pixel(terrain_color);
+        }
+
+        let mut pixel = to_rgba(terrain_color);
+        pixel[3] = 255;
+        if let Some(player) = players.get(territory_map[position]) {
+            blend(&mut pixel, player.principal_color, MINIMAP_TERRITORY_TINT_ALPHA);
+        }
+        pixel
+    })
+}
+
+/// Software rendering of the whole map with terrains, territories and their borders, buildings and units
+///
+/// # Arguments
+///
+/// * `game` - Initialized game (OpenGL context is not needed)
+/// * `cell_size` - Side of a map cell in pixels
+///
+pub fn snapshot_image(game: &Game, cell_size: u32) -> RgbaImage {
+    let terrain_map = game.terrain_map.borrow();
+    let territory_map = &game.territory_map;
+    let cell_size = cell_size.max(4);
+    let mut image = RgbaImage::new(terrain_map.width as u32 * cell_size, terrain_map.height as u32 * cell_size);
+
+    // Cells: terrain color tinted with owner color
+    for ((i, j), terrain) in terrain_map.iter_positions() {
+        let mut cell_color = to_rgba(terrain.upgrade().map_or(Terrain::default().color, |terrain| terrain.color));
+        cell_color[3] = 255;
+        let owner = game.players.get(territory_map[(i, j)]);
+        if let Some(player) = owner {
+            blend(&mut cell_color, player.principal_color, TERRITORY_TINT_ALPHA);
+        }
+
+        let (x0, y0) = (j as u32 * cell_size, i as u32 * cell_size);
+        for y in y0..(y0 + cell_size) {
+            for x in x0..(x0 + cell_size) {
+                image.put_pixel(x, y, cell_color);
+            }
+        }
+
+        // Borders: cell edges facing a cell of another owner (or the map edge)
+        if let Some(player) = owner {
+            let border_color = to_rgba(player.principal_color);
+            let border_width = (cell_size / 8).max(1);
+            for (di, dj) in Neighbourhood::VonNeumann.directions() {
+                let neighbour = territory_map.get((i as i32 + di, j as i32 + dj));
+                if neighbour == Some(&territory_map[(i, j)]) {
+                    continue;
+                }
+                for k in 0..cell_size {
+                    for w in 0..border_width {
+                        let (x, y) = match (di, dj) {
+                            (-1, 0) => (x0 + k, y0 + w),
+                            (1, 0) => (x0 + k, y0 + cell_size - 1 - w),
+                            (0, -1) => (x0 + w, y0 + k),
+                            _ => (x0 + cell_size - 1 - w, y0 + k)
+                        };
+                        image.put_pixel(x, y, border_color);
+                    }
+                }
+            }
+        }
+    }
+
+    // Buildings: squares with a quarter cell padding, units: discs with the same padding
+    let padding = cell_size / 4;
+    let outline = (cell_size / 16).max(1);
+    for player in game.players.iter() {
+        let principal_color = to_rgba(player.principal_color);
+        let secondary_color = to_rgba(player.secondary_color);
+
+        for building in player.buildings.iter() {
+            let (i, j) = building.borrow().position;
+            let (x0, y0) = (j as u32 * cell_size + padding, i as u32 * cell_size + padding);
+            let side = cell_size - 2 * padding;
+            for y in 0..side {
+                for x in 0..side {
+                    let on_outline = x < outline || y < outline || x >= side - outline || y >= side - outline;
+                    image.put_pixel(x0 + x, y0 + y, if on_outline {secondary_color} else {principal_color});
+                }
+            }
+        }
+
+        for unit in player.units.iter() {
+            let (i, j) = unit.borrow().position;
+            let center = ((j as f64 + 0.5) * cell_size as f64, (i as f64 + 0.5) * cell_size as f64);
+            let radius = (cell_size - 2 * padding) as f64 / 2.0;
+            let (x0, y0) = (j as u32 * cell_size, i as u32 * cell_size);
+            for y in y0..(y0 + cell_size) {
+                for x in x0..(x0 + cell_size) {
+                    let (dx, dy) = (x as f64 + 0.5 - center.0, y as f64 + 0.5 - center.1);
+                    let distance = (dx * dx + dy * dy).sqrt();
+                    if distance <= radius - outline as f64 {
+                        image.put_pixel(x, y, principal_color);
+                    }
+                    else if distance <= radius {
+                        image.put_pixel(x, y, secondary_color);
+                    }
+                }
+            }
+        }
+    }
+
+    image
+}
+
+pub fn export_height_map(height_map: &Map<f64>, path: &Path) -> Result<(), String> {
+    save(DynamicImage::ImageLuma8(height_map_image(height_map)), path)
+}
+
+pub fn export_terrain_map(terrain_map: &Map<Weak<Terrain>>, path: &Path) -> Result<(), String> {
+    save(DynamicImage::ImageRgba8(terrain_map_image(terrain_map)), path)
+}
+
+pub fn export_territory_map(territory_map: &Map<usize>, players: &[Player], path: &Path) -> Result<(), String> {
+    save(DynamicImage::ImageRgba8(territory_map_image(territory_map, players)), path)
+}
+
+pub fn export_snapshot(game: &Game, cell_size: u32, path: &Path) -> Result<(), String> {
+    save(DynamicImage::ImageRgba8(snapshot_image(game, cell_size)), path)
+}
+
+/// Write height, terrain, territory and snapshot PNG images of a game into a directory
+/// (created if needed), returns written file paths
+pub fn export_game(game: &Game, directory: &Path, cell_size: u32) -> Result<Vec<String>, String> {
+    std::fs::create_dir_all(directory)
+        .map_err(|error| format!("Cannot create export directory '{}': {}", directory.display(), error))?;
+
+    let height_path = directory.join("height.png");
+    let terrain_path = directory.join("terrain.png");
+    let territory_path = directory.join("territory.png");
+    let snapshot_path = directory.join("snapshot.png");
+
+    export_height_map(&game.height_map, &height_path)?;
+    export_terrain_map(&game.terrain_map.borrow(), &terrain_path)?;
+    export_territory_map(&game.territory_map, &game.players, &territory_path)?;
+    export_snapshot(game, cell_size, &snapshot_path)?;
+
+    Ok([height_path, terrain_path, territory_path, snapshot_path].iter().map(|path| path.display().to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    // Player with a base at a position
+    fn player(num: usize, base_position: (usize, usize)) -> Player {
+        Player::new(num, base_position, [1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0])
+    }
+
+    #[test]
+    fn height_map_image_has_a_pixel_per_cell_with_clamped_heights() {
+        let mut height_map = Map::new(5, 3, 0.5);
+        height_map[(0, 0)] = -0.5;
+        height_map[(2, 4)] = 1.5;
+        let image = height_map_image(&height_map);
+        assert_eq!(image.dimensions(), (5, 3));
+        assert_eq!(image.get_pixel(0, 0)[0], 0);
+        assert_eq!(image.get_pixel(4, 2)[0], 255);
+        assert_eq!(image.get_pixel(1, 0)[0], 128);
+    }
+
+    #[test]
+    fn unowned_cells_are_transparent() {
+        let players = vec![player(0, (0, 0)), player(1, (1, 1))];
+        assert_eq!(territory_pixel(0, &players), Rgba([255, 0, 0, 255]));
+        assert_eq!(territory_pixel(players.len(), &players), NO_TERRITORY_COLOR);
+        assert_eq!(territory_pixel(usize::MAX, &players), NO_TERRITORY_COLOR);
+    }
+
+    #[test]
+    fn snapshot_image_has_a_square_per_cell() {
+        let game = Game {
+            terrain_map: Rc::new(RefCell::new(Map::new(5, 3, Weak::new()))),
+            territory_map: Map::new(5, 3, 1),
+            players: vec![player(0, (1, 2))],
+            ..Game::default()
+        };
+        let image = snapshot_image(&game, 16);
+        assert_eq!(image.dimensions(), (80, 48));
+
+        // Building drawn in the middle of its cell
+        assert_eq!(*image.get_pixel(2 * 16 + 8, 16 + 8), Rgba([255, 0, 0, 255]));
+        // Cells without terrain are opaque black
+        assert_eq!(*image.get_pixel(8, 8), Rgba([0, 0, 0, 255]));
+
+        // Cell size is at least 4 pixels
+        assert_eq!(snapshot_image(&game, 1).dimensions(), (20, 12));
+    }
+}
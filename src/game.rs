@@ -1,6 +1,8 @@
-use std::collections::VecDeque;
+use std::collections::{VecDeque, HashMap};
 use std::cell::RefCell;
 use std::rc::{Rc, Weak};
+use std::path::Path;
+use std::time::Instant;
 
 use piston::input::{
     RenderArgs, 
@@ -8,23 +10,30 @@ use piston::input::{
     Event, 
     MouseCursorEvent, 
     PressEvent, 
-    Button, 
-    MouseButton, 
     ReleaseEvent, 
-    MouseScrollEvent
+    MouseScrollEvent, 
+    UpdateEvent, 
+    CursorEvent, 
+    Button
 };
 
-use piston::input::keyboard::Key;
-
 use opengl_graphics::{
     GlGraphics, 
-    GlyphCache
+    GlyphCache, 
+    Texture, 
+    TextureSettings, 
+    Filter
 };
 
+use serde::Deserialize;
+
+use ::image::RgbaImage;
+
 use graphics::*;
 use graphics::rectangle::rectangle_by_corners;
 
 use graphics::text::Text;
+use graphics::character::CharacterCache;
 use graphics::rectangle::Rectangle;
 use graphics::ellipse::Ellipse;
 
@@ -34,8 +43,42 @@ use crate::utils::{
 
 use crate::map::{
     Map, 
-    diamond_square, 
-    noise_map
+    WrapMode, 
+    Neighbourhood
+};
+
+use crate::placement::{
+    start_positions, 
+    passable_neighbors
+};
+
+use crate::map_generator::{
+    map_generator, 
+    map_generators, 
+    height_map_from_image
+};
+
+use crate::erosion::{
+    ThermalErosionParameters, 
+    HydraulicErosionParameters, 
+    thermal_erosion, 
+    hydraulic_erosion
+};
+
+use crate::biome::{
+    BiomeTable, 
+    ClimateParameters, 
+    load_terrain_data, 
+    temperature_map, 
+    moisture_map
+};
+
+use crate::relief::{
+    TerrainRendering, 
+    ReliefParameters, 
+    hillshade_map, 
+    shade_color, 
+    contour_level
 };
 
 pub use crate::player::{
@@ -45,34 +88,103 @@ pub use crate::player::{
 };
 
 use crate::distance::{
-    NullDistance2D, 
-    EuclideanDistanceWHeight2D
+    Distance2D, 
+    EuclideanDistance2D, 
+    EuclideanDistanceWHeight2D, 
+    WrappedDistance2D
 };
 use crate::constraint::{
+    PositionConstraint, 
     TerrainConstraint, 
     UnitConstraint, 
     BuildingConstraint
 };
-use crate::path_planning::astar_2d_map;
+use crate::path_planning::FlowField;
+use crate::path_cache::PathCache;
+use crate::history::{History, UnitMove};
+use crate::hierarchical_path_planning::HierarchicalMap;
+use crate::scenario::{
+    Scenario, 
+    MapSource, 
+    VictoryCondition
+};
+use crate::export::{
+    export_game, 
+    minimap_image, 
+    hidden_cell_pixel, 
+    territory_map_image, 
+    territory_pixel, 
+    to_rgba
+};
+use crate::input::{Action, KeyBindings};
+use crate::tileset::Tileset;
+use crate::animation::{Animation, AnimationQueue};
+
+// Maps with at least this number of cells use hierarchical path planning, with clusters of this side, for paths
+// whose ends are at least this far apart (shorter ones are cached and replanned incrementally)
+const HIERARCHICAL_PATH_PLANNING_MIN_CELLS: usize = 257 * 257;
+const HIERARCHICAL_PATH_PLANNING_CLUSTER_SIZE: usize = 16;
+const HIERARCHICAL_PATH_PLANNING_MIN_DISTANCE: f64 = 4.0 * HIERARCHICAL_PATH_PLANNING_CLUSTER_SIZE as f64;
+
+// Zoom factor of one scroll step (or zoom key press)
+const ZOOM_PER_SCROLL_STEP: f64 = 1.25;
+
+// Rate at which animated view transitions close the gap to their target, per second
+const CAMERA_SMOOTHING: f64 = 10.0;
+
+// Panning speed in view sizes per second, increasing while pan keys are held, and window border
+// width in pixels where the mouse pans the view
+const PAN_SPEED: f64 = 0.5;
+const PAN_ACCELERATION: f64 = 1.5;
+const PAN_MAX_SPEED: f64 = 2.0;
+const EDGE_PAN_MARGIN: f64 = 8.0;
+
+// Opacity of player colors painted over terrain in the view
+const TERRITORY_OVERLAY_ALPHA: f32 = 0.35;
+
+// Smallest cell size in pixels at which contour lines and detailed unit and building shapes are drawn (plain
+// squares batched by color are drawn below it)
+const CONTOUR_MIN_CELL_PIXELS: f64 = 4.0;
+const DETAILED_SPRITE_MIN_CELL_PIXELS: f64 = 8.0;
+
+// Weight of the last frame in smoothed frame and render times
+const FRAME_TIME_SMOOTHING: f64 = 0.05;
+
+// Distance in cells up to which units and buildings see in hot-seat mode
+const VISION_RANGE: i32 = 4;
+
+// Title size of the turn handover screen
+const HANDOVER_FONT_SIZE: u32 = 32;
+
+// HUD panel text size and inner margin, in pixels
+const HUD_FONT_SIZE: u32 = 14;
+const HUD_PADDING: f64 = 12.0;
+
+// Colors given to players in order
+const PLAYER_COLORS: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 1.0],
+    [0.0, 0.0, 1.0, 1.0],
+    [0.0, 0.8, 0.0, 1.0],
+    [1.0, 0.8, 0.0, 1.0]
+];
 
 // Structure used to holding terrain information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct Terrain {
     pub name: String,
     pub color: [f32; 4],
-    pub height_interval: (f64, f64)
+    pub height_interval: (f64, f64),
+    pub impractical: bool
 }
 
 impl Default for Terrain {
-    fn default() -> Self {Terrain {name: String::from("None"), color: [0.0, 0.0, 0.0, 0.0], height_interval: (-1.0, -1.0)}}
+    fn default() -> Self {Terrain {name: String::from("None"), color: [0.0, 0.0, 0.0, 0.0], height_interval: (-1.0, -1.0), impractical: false}}
 }
 
 impl PartialEq for Terrain {
     fn eq(&self, other: &Self) -> bool {
-        if self.name != other.name {false}
-        else if self.color != other.color {false}
-        else if self.height_interval != other.height_interval {false}
-        else {true}
+        self.name == other.name && self.color == other.color && self.height_interval == other.height_interval
     }
 }
 
@@ -93,8 +205,52 @@ pub struct Game<'g> {
     pub view_in_window_width: f64,
     pub view_in_window_height: f64,
 
+    // Window dimensions, the HUD panel takes the space right of the view
+    pub window_width: f64,
+    pub window_height: f64,
+
+    // Minimap at the bottom of the HUD panel, its texture is rebuilt when terrain or territory changes
+    pub minimap_texture: Option<Texture>,
+    pub minimap_outdated: bool,
+    pub minimap_hovered_position: Option<(f64, f64)>,
+    pub minimap_dragging: bool,
+
+    // Terrain and territory layers drawn from textures with one pixel per cell, the terrain one being rebuilt when
+    // outdated and the territory one updated with the cells whose owner changed (rebuilt if there is no image)
+    pub terrain_texture: Option<Texture>,
+    pub terrain_outdated: bool,
+    pub territory_image: Option<RgbaImage>,
+    pub territory_texture: Option<Texture>,
+    pub territory_changes: Vec<(usize, usize)>,
+
+    // Sprites of units, buildings and terrains (primitives are drawn without them) and their atlas texture
+    pub tileset: Option<Tileset>,
+    pub tileset_texture: Option<Texture>,
+
+    // Hot-seat mode: players sharing the screen only see cells near their units and buildings (others are covered by
+    // the fog texture), and a handover screen hides the map until the next player is ready
+    pub hot_seat: bool,
+    pub handover: bool,
+    pub visibility_map: Option<Map<bool>>,
+    pub visibility_outdated: bool,
+    pub fog_texture: Option<Texture>,
+
+    // View of each player, restored on their turn
+    pub player_views: Vec<Option<[f64; 4]>>,
+
+    // Unit moves and attacks shown after the game state is updated
+    pub animations: AnimationQueue,
+
+    // Smoothed time between two frames and time spent rendering one, in seconds
+    pub frame_time: f64,
+    pub render_time: f64,
+    pub last_frame_instant: Option<Instant>,
+
+    // Map dimensions (computed from map size level when left to 0)
     pub map_size_level: u32,
-    pub map_size: usize,
+    pub map_width: usize,
+    pub map_height: usize,
+    pub wrap_mode: WrapMode,
 
     pub view_in_map_i: f64,
     pub view_in_map_j: f64,
@@ -102,10 +258,28 @@ pub struct Game<'g> {
     pub view_in_map_width: f64,
     pub view_in_map_height: f64,
 
+    // Map generation parameters
+    pub map_preset: String,
+    pub height_map_path: Option<String>,
+    pub map_seed: Option<u64>,
+
+    // Seed the current map was generated with, shown in the HUD to generate it again
+    pub current_map_seed: u64,
+    pub thermal_erosion: ThermalErosionParameters,
+    pub hydraulic_erosion: HydraulicErosionParameters,
+    pub climate: ClimateParameters,
+    pub terrain_data_path: String,
+
     // Game variables
     pub unit_default_speed: f64,
     pub player_num: usize,
 
+    // Scenario used for initial setup (random setup if None) and game end conditions
+    pub scenario: Option<Scenario>,
+    pub victory_conditions: Vec<VictoryCondition>,
+    pub turn_num: usize,
+    pub winner: Option<usize>,
+
     // Player vector
     pub players: Vec<Player>,
 
@@ -118,130 +292,153 @@ pub struct Game<'g> {
     // Territory map
     pub territory_map: Map<usize>,
 
+    // Passable cells owned by each player and in the whole map, kept up to date with territory changes
+    pub territory_cells_num: Vec<usize>,
+    pub passable_cells_num: usize,
+
     // Height map
     pub height_map: Map<f64>,
 
+    // Climate maps used to choose biomes
+    pub temperature_map: Map<f64>,
+    pub moisture_map: Map<f64>,
+
     // Terrain map
     pub terrain_map: Rc<RefCell<Map<Weak<Terrain>>>>,
 
     pub terrains: Vec<Rc<Terrain>>,
+    pub biome_table: BiomeTable,
     pub color_ramp_value: Vec<f64>,
     pub color_ramp_color: Vec<[f32; 4]>,
 
+    // Terrain drawn with flat terrain colors or with relief (color ramp, hillshading and contour lines)
+    pub terrain_rendering: TerrainRendering,
+    pub relief: ReliefParameters,
+    pub hillshade_map: Map<f64>,
+
     pub active_player: usize,
     pub active_unit: Weak<RefCell<Unit>>,
     pub active_unit_planned_path: Option<VecDeque<(i32, i32, f64)>>,
+    pub path_cache: PathCache,
+    pub hierarchical_map: Option<HierarchicalMap>,
+
+    // Units of the active player moved together along a flow field toward their destinations,
+    // attacking the enemy on the group target if there is one
+    pub selected_units: Vec<Weak<RefCell<Unit>>>,
+    pub group_flow_field: Option<FlowField>,
+    pub group_target: Option<(usize, usize)>,
+
+    // Reversible actions of the active player turn
+    pub history: History,
+
+    // Selections stored on number keys
+    pub control_groups: [Vec<Weak<RefCell<Unit>>>; 10],
+
+    // Mapping of inputs to actions
+    pub key_bindings: KeyBindings,
+
     pub current_mouse_position: Option<[f64; 2]>,
+    pub cursor_in_window: bool,
+
+    // View (i, j, height, width) an animated transition is moving to
+    pub view_target: Option<[f64; 4]>,
+
+    // Buttons of pan actions held down and for how long, last mouse position of a drag panning
+    pub held_pan_buttons: Vec<(Button, Action)>,
+    pub pan_hold_time: f64,
+    pub drag_pan_position: Option<[f64; 2]>,
 
     pub current_underlying_cell: Option<[usize; 2]>,
     pub pressed_map_cell: Option<[usize; 2]>,
     pub released_map_cell: Option<[usize; 2]>,
 }
 
+// Upload an image to a texture, created (with nearest filtering) if there is none with the image size
+fn update_texture(texture: &mut Option<Texture>, image: &RgbaImage) {
+    match texture {
+        Some(texture) if texture.get_size() == image.dimensions() => texture.update(image),
+        _ => *texture = Some(Texture::from_image(image, &TextureSettings::new().filter(Filter::Nearest)))
+    }
+}
+
+// Pieces of a view interval along an axis over the copies of the map, as (start in the map, start in the view, length)
+fn axis_pieces(start: f64, length: f64, size: f64, wraps: bool) -> Vec<(f64, f64, f64)> {
+    let mut pieces = Vec::new();
+    let copies = if wraps {(start / size).floor() as i64..((start + length) / size).ceil() as i64} else {0..1};
+    for copy in copies {
+        let offset = copy as f64 * size;
+        let (piece_start, piece_end) = (start.max(offset), (start + length).min(offset + size));
+        if piece_end > piece_start {
+            pieces.push((piece_start - offset, piece_start - start, piece_end - piece_start));
+        }
+    }
+    pieces
+}
+
+// Visible copy of a unit with its offset in cells while moving, its player and whether it has a standing order
+type DrawnUnit = ((i32, i32), (f64, f64), usize, bool);
+
 impl<'g> Game<'g> {
     
-    // Init game
-    pub fn init(&mut self) {
+    // Init game, from scenario if there is one
+    pub fn init(&mut self) -> Result<(), String> {
+
+        // Scenario map settings replace given ones
+        if let Some(scenario) = &self.scenario {
+            self.map_width = scenario.map.width;
+            self.map_height = scenario.map.height;
+            self.wrap_mode = scenario.map.wrap;
+            match &scenario.map.source {
+                MapSource::Generator {preset, seed} => {
+                    self.map_preset = preset.clone();
+                    self.map_seed = *seed;
+                    self.height_map_path = None;
+                },
+                MapSource::Image {path} => {
+                    self.height_map_path = Some(path.clone());
+                }
+            }
+            self.player_num = scenario.players.len();
+            self.victory_conditions = scenario.victory_conditions.clone();
+        }
 
-        // Compute map size
-        self.map_size = (2 as usize).pow(4+self.map_size_level)+1;
+        // Compute map dimensions if not given
+        if self.map_width == 0 || self.map_height == 0 {
+            self.map_width = 2_usize.pow(4+self.map_size_level)+1;
+            self.map_height = self.map_width;
+        }
 
         // Initialize unit map and building map with None
-        self.unit_map = Rc::new(RefCell::new(Map::<Weak<RefCell<Unit>>>::new(self.map_size, self.map_size, Weak::new())));
-        self.building_map = Rc::new(RefCell::new(Map::<Weak<RefCell<Building>>>::new(self.map_size, self.map_size, Weak::new())));
-        self.territory_map = Map::new(self.map_size, self.map_size, self.player_num);
+        self.unit_map = Rc::new(RefCell::new(self.new_map(Weak::new())));
+        self.building_map = Rc::new(RefCell::new(self.new_map(Weak::new())));
+        self.territory_map = self.new_map(self.player_num);
 
-        // Generation of playable map
-        self.generate_map();
+        // Load terrains and biomes
+        let terrain_data = load_terrain_data(&self.terrain_data_path)?;
+        self.terrains = terrain_data.terrains.into_iter().map(Rc::new).collect();
+        self.biome_table = terrain_data.biomes;
 
-        // Initialize players 
-        // TODO : Initialize base position for all players with clever algorithm
-        self.players = Vec::new();
-        self.players.push(
-            Player::new(
-                0, 
-                (0, 0), 
-                [1.0, 0.0, 0.0, 1.0], 
-                [0.0, 0.0, 0.0, 1.0]
-            )
-        );
-        self.players.push(
-            Player::new(
-                1, 
-                (self.map_size - 1, self.map_size - 1), 
-                [0.0, 0.0, 1.0, 1.0], 
-                [0.0, 0.0, 0.0, 1.0]
-            )
-        );
+        // Generation of playable map
+        self.generate_map()?;
+
+        // Initialize players
+        match self.scenario.take() {
+            Some(scenario) => {
+                let result = self.init_scenario_players(&scenario);
+                self.scenario = Some(scenario);
+                result?;
+            },
+            None => self.init_default_players()
+        }
 
         // Add reference to player buildings in buildings map
         for player in &self.players {
             for building in &player.buildings {
-                (self.building_map.borrow_mut())[building.borrow().position] = Rc::downgrade(&building);
+                (self.building_map.borrow_mut())[building.borrow().position] = Rc::downgrade(building);
                 self.territory_map[building.borrow().position] = building.borrow().player;
             }
         }
 
-        // Add first units
-        // TODO : Make a function to generate unit near to base building
-        self.players[0].units.push(
-            Rc::new(
-                RefCell::new(
-                    Unit {
-                        player: 0,
-                        position: (0, 1),
-                        damage: 1.0,
-                        health: 1.0,
-                        speed: self.unit_default_speed,
-                        remaining_moves: self.unit_default_speed
-                    }
-                )
-            )
-        );
-        self.players[0].units.push(
-            Rc::new(
-                RefCell::new(
-                    Unit {
-                        player: 0,
-                        position: (1, 0),
-                        damage: 1.0,
-                        health: 1.0,
-                        speed: self.unit_default_speed,
-                        remaining_moves: self.unit_default_speed
-                    }
-                )
-            )
-        );
-
-        self.players[1].units.push(
-            Rc::new(
-                RefCell::new(
-                    Unit {
-                        player: 1,
-                        position: (self.map_size - 1, self.map_size - 2),
-                        damage: 1.0,
-                        health: 1.0,
-                        speed: self.unit_default_speed,
-                        remaining_moves: self.unit_default_speed
-                    }
-                )
-            )
-        );
-        self.players[1].units.push(
-            Rc::new(
-                RefCell::new(
-                    Unit {
-                        player: 1,
-                        position: (self.map_size - 2, self.map_size - 1),
-                        damage: 1.0,
-                        health: 1.0,
-                        speed: self.unit_default_speed,
-                        remaining_moves: self.unit_default_speed
-                    }
-                )
-            )
-        );
-       
         // Add reference to player units in units map
         for player in &self.players {
             for unit in &player.units {
@@ -252,50 +449,215 @@ impl<'g> Game<'g> {
 
         // Let the first player be the current active player
         self.active_player = 0;
-
-        // Look at active player base position
+        self.turn_num = 0;
+        self.winner = None;
+        self.count_territory();
+        self.minimap_outdated = true;
+        self.territory_image = None;
+        self.player_views = vec![None; self.player_num];
+        self.visibility_outdated = true;
+        self.handover = self.hot_seat;
+
+        // Look at active player base position with overview zoom
+        self.look_at_overview();
         self.look_at_active_user_base();
+        self.skip_view_transition();
+
+        Ok(())
     }
 
-    // Map generation function
-    pub fn generate_map(&mut self) {
+    // Players with a base as far as possible from each other and first units around it
+    fn init_default_players(&mut self) {
+        let first_units_num = 2;
+        let passable_map = self.passable_map();
+        let base_positions = start_positions(&passable_map, self.player_num, first_units_num, self.current_map_seed);
+        self.players = Vec::new();
+        for (num, base_position) in base_positions.iter().enumerate() {
+            self.players.push(
+                Player::new(
+                    num, 
+                    *base_position, 
+                    PLAYER_COLORS[num % PLAYER_COLORS.len()], 
+                    [0.0, 0.0, 0.0, 1.0]
+                )
+            );
+        }
 
-        // Generate procedurally height map
-        self.height_map = Map::new(self.map_size, self.map_size, 0.0);
-        let lacunarity = 2.0;
-        self.height_map = noise_map(
-            self.map_size,
-            16,
-            lacunarity,
-            lacunarity, 
-            1.0, 
-            false
-        );
+        // Add first units around base building
+        // TODO : Make a function to generate unit near to base building
+        for player in self.players.iter_mut() {
+            let base_position = player.buildings[0].borrow().position;
+            for position in passable_neighbors(&passable_map, base_position).into_iter().take(first_units_num) {
+                player.units.push(
+                    Rc::new(
+                        RefCell::new(
+                            Unit {
+                                player: player.num,
+                                position,
+                                damage: 1.0,
+                                health: 1.0,
+                                speed: self.unit_default_speed,
+                                remaining_moves: self.unit_default_speed,
+                                goal: None
+                            }
+                        )
+                    )
+                );
+            }
+        }
+    }
 
-        // Assign Terrain to map cell according to cell height
-        // TODO : Pass terrain information by configuration file to reduce code complexity
-        let terrain_vec: Vec<Terrain> = vec![
-            Terrain {name: String::from("DeepWater"), color: [0.007, 0.176, 0.357, 1.0], height_interval: (0.0, 0.2)},
-            Terrain {name: String::from("CoastalWater"), color: [0.051, 0.286, 0.404, 1.0], height_interval: (0.2, 0.275)},
-            Terrain {name: String::from("Sand"), color: [0.98, 0.84, 0.45, 1.0], height_interval: (0.275, 0.3)},
-            Terrain {name: String::from("Grass"), color: [0.204, 0.412, 0.180, 1.0], height_interval: (0.3, 0.7)},
-            Terrain {name: String::from("Mountain"), color: [0.557, 0.541, 0.341, 1.0], height_interval: (0.7, 0.90)},
-            Terrain {name: String::from("SnowyPeak"), color: [1.0, 1.0, 1.0, 1.0], height_interval: (0.90, 1.0 + 0.01)},
-        ];
+    // Players with units, buildings and territory of the scenario, checked against generated terrain
+    fn init_scenario_players(&mut self, scenario: &Scenario) -> Result<(), String> {
+        scenario.validate_terrain(&self.terrain_map.borrow())
+            .map_err(|error| format!("Invalid scenario '{}': {}", scenario.name, error))?;
 
-        self.terrains.extend(terrain_vec.into_iter().map(|x| Rc::new(x)));
-        
-        self.terrain_map = Rc::new(RefCell::new(Map::<Weak<Terrain>>::new(self.map_size, self.map_size, Weak::new())));
-        for i in 0..self.map_size {
-            for j in 0..self.map_size {
-                for terrain in self.terrains.iter() {
-                    let height = self.height_map[(i, j)];
-                    if height >= terrain.height_interval.0 && height < terrain.height_interval.1 {
-                        self.terrain_map.borrow_mut()[(i, j)] = Rc::downgrade(terrain);
-                    }
+        self.players = Vec::new();
+        for (num, scenario_player) in scenario.players.iter().enumerate() {
+            let mut player = Player {
+                num,
+                buildings: Vec::new(),
+                units: Vec::new(),
+                principal_color: scenario_player.color.unwrap_or(PLAYER_COLORS[num % PLAYER_COLORS.len()]),
+                secondary_color: [0.0, 0.0, 0.0, 1.0]
+            };
+
+            for building in scenario_player.buildings.iter() {
+                player.buildings.push(Rc::new(RefCell::new(Building {
+                    player: num,
+                    position: building.position,
+                    damage: building.damage,
+                    health: building.health
+                })));
+            }
+
+            for unit in scenario_player.units.iter() {
+                let speed = unit.speed.unwrap_or(self.unit_default_speed);
+                player.units.push(Rc::new(RefCell::new(Unit {
+                    player: num,
+                    position: unit.position,
+                    damage: unit.damage,
+                    health: unit.health,
+                    speed,
+                    remaining_moves: speed,
+                    goal: None
+                })));
+            }
+
+            for position in scenario_player.territory.iter() {
+                self.territory_map[*position] = num;
+            }
+
+            self.players.push(player);
+        }
+
+        Ok(())
+    }
+
+    // Map generation function, failing on unknown preset or unreadable height map image
+    pub fn generate_map(&mut self) -> Result<(), String> {
+
+        // Same seed gives the same map, a random one is drawn if none is given
+        let seed = self.map_seed.unwrap_or_else(rand::random);
+
+        if let Some(height_map_path) = &self.height_map_path {
+            // Load hand-crafted height map as is (no erosion)
+            self.height_map = height_map_from_image(height_map_path, self.map_width, self.map_height, self.wrap_mode)?;
+        }
+        else {
+            // Generate procedurally height map with selected preset
+            let generator = map_generator(&self.map_preset).ok_or_else(|| {
+                let presets: Vec<String> = map_generators().iter().map(|generator| generator.name().to_string()).collect();
+                format!("Unknown map preset '{}', expected one of {}", self.map_preset, presets.join(", "))
+            })?;
+            self.height_map = generator.generate(self.map_width, self.map_height, self.wrap_mode, seed);
+
+            // Erode height map to get realistic valleys and plateaus
+            let hydraulic_erosion_parameters = HydraulicErosionParameters {
+                seed: self.hydraulic_erosion.seed.or(Some(seed)),
+                ..self.hydraulic_erosion
+            };
+            hydraulic_erosion(&mut self.height_map, &hydraulic_erosion_parameters);
+            thermal_erosion(&mut self.height_map, &self.thermal_erosion);
+            self.height_map.normalize();
+        }
+        self.current_map_seed = seed;
+
+        // Illumination of slopes used by relief rendering
+        self.hillshade_map = hillshade_map(&self.height_map, &self.relief);
+
+        // Generate climate maps used to choose biomes
+        self.temperature_map = temperature_map(&self.height_map, &self.climate, seed.wrapping_add(1));
+        self.moisture_map = moisture_map(&self.height_map, &self.climate, seed.wrapping_add(2));
+
+        // Assign Terrain to map cell according to cell height, then to climate for biome heights
+        let mut terrain_map = self.new_map(Weak::new());
+        for (pos, &height) in self.height_map.iter_positions() {
+            if self.biome_table.contains_height(height) {
+                let terrain_index = self.biome_table.terrain_index(self.temperature_map[pos], self.moisture_map[pos]);
+                terrain_map[pos] = Rc::downgrade(&self.terrains[terrain_index]);
+                continue;
+            }
+
+            for terrain in self.terrains.iter() {
+                if height >= terrain.height_interval.0 && height < terrain.height_interval.1 {
+                    terrain_map[pos] = Rc::downgrade(terrain);
                 }
             }
         }
+        self.terrain_map = Rc::new(RefCell::new(terrain_map));
+
+        // Planned path and previous searches may now cross impractical terrains
+        self.active_unit_planned_path = None;
+        self.group_flow_field = None;
+        self.path_cache.clear();
+        self.history.clear();
+        self.animations.clear();
+        self.minimap_outdated = true;
+        self.terrain_outdated = true;
+        self.visibility_outdated = true;
+        self.count_territory();
+
+        // Hierarchical abstraction of terrain passability for large maps, rebuilt whenever terrain changes
+        self.hierarchical_map = None;
+        if self.map_width * self.map_height >= HIERARCHICAL_PATH_PLANNING_MIN_CELLS {
+            let distance = self.move_distance();
+            self.hierarchical_map = Some(HierarchicalMap::new(self.passable_map(), HIERARCHICAL_PATH_PLANNING_CLUSTER_SIZE, &distance));
+        }
+
+        Ok(())
+    }
+
+    // Create a map with game dimensions and wrap mode
+    fn new_map<T: Clone>(&self, value: T) -> Map<T> {
+        let mut map = Map::new(self.map_width, self.map_height, value);
+        map.wrap = self.wrap_mode;
+        map
+    }
+
+    // Map of cells whose terrain is practicable
+    fn passable_map(&self) -> Map<bool> {
+        self.terrain_map.borrow().map(|terrain| terrain.upgrade().is_none_or(|terrain| !terrain.impractical))
+    }
+
+    // Cost of moving between two neighbour cells, going up or down hills costing more
+    fn move_distance(&self) -> WrappedDistance2D<EuclideanDistanceWHeight2D<'_>> {
+        WrappedDistance2D {
+            distance: EuclideanDistanceWHeight2D {
+                height_map: &self.height_map
+            },
+            map_size: (self.map_height as f64, self.map_width as f64),
+            wrap_mode: self.wrap_mode
+        }
+    }
+
+    // Select the map preset following the current one (leaving any imported height map)
+    fn next_map_preset(&mut self) {
+        self.height_map_path = None;
+        let presets: Vec<String> = map_generators().iter().map(|generator| generator.name().to_string()).collect();
+        let current = presets.iter().position(|preset| *preset == self.map_preset).unwrap_or(0);
+        self.map_preset = presets[(current + 1) % presets.len()].clone();
+        println!("Map preset : {}", self.map_preset);
     }
 
     // Utility functions
@@ -303,10 +665,35 @@ impl<'g> Game<'g> {
         (self.view_in_window_width / self.view_in_map_width, self.view_in_window_height / self.view_in_map_height)
     }
 
+    // Visible cells bounds, not clamped along wrapped axes where cells repeat endlessly
     fn visible_map_bounds(&self) -> (i32, i32, i32, i32) {
-        (std::cmp::max(self.view_in_map_i.floor() as i32, 0), std::cmp::max(self.view_in_map_j.floor() as i32, 0),
-        std::cmp::min((self.view_in_map_i + self.view_in_map_height).ceil() as i32, self.map_size as i32), 
-        std::cmp::min((self.view_in_map_j + self.view_in_map_width).ceil() as i32, self.map_size as i32))
+        let (mut i1, mut j1) = (self.view_in_map_i.floor() as i32, self.view_in_map_j.floor() as i32);
+        let (mut i2, mut j2) = ((self.view_in_map_i + self.view_in_map_height).ceil() as i32, (self.view_in_map_j + self.view_in_map_width).ceil() as i32);
+        if !self.wrap_mode.wraps_vertically() {
+            i1 = i1.max(0);
+            i2 = i2.min(self.map_height as i32);
+        }
+        if !self.wrap_mode.wraps_horizontally() {
+            j1 = j1.max(0);
+            j2 = j2.min(self.map_width as i32);
+        }
+        (i1, j1, i2, j2)
+    }
+
+    // Copy of a map position inside visible bounds (cells repeat along wrapped axes)
+    fn visible_position(&self, map_position: (i32, i32)) -> Option<(i32, i32)> {
+        let (i1, j1, i2, j2) = self.visible_map_bounds();
+        let (height, width) = (self.map_height as i32, self.map_width as i32);
+        let mut i = map_position.0;
+        let mut j = map_position.1;
+        if self.wrap_mode.wraps_vertically() {
+            i = i1 + (i - i1).rem_euclid(height);
+        }
+        if self.wrap_mode.wraps_horizontally() {
+            j = j1 + (j - j1).rem_euclid(width);
+        }
+
+        if is_in_rect((i, j), (i1, j1, i2, j2), false) {Some((i, j))} else {None}
     }
 
     fn i_to_y(&self, i: i32) -> f64 {
@@ -319,11 +706,141 @@ impl<'g> Game<'g> {
 
     fn window_position_to_map_position(&self, window_position: (f64, f64)) -> (i32, i32) {
         let (x, y) = window_position;
-        let i = (((y - self.view_in_window_y) / self.view_in_window_height) * self.view_in_map_height as f64 + self.view_in_map_i as f64) as i32;
-        let j = (((x - self.view_in_window_x) / self.view_in_window_width) * self.view_in_map_width as f64 + self.view_in_map_j as f64) as i32;
+        let i = (((y - self.view_in_window_y) / self.view_in_window_height) * self.view_in_map_height + self.view_in_map_i).floor() as i32;
+        let j = (((x - self.view_in_window_x) / self.view_in_window_width) * self.view_in_map_width + self.view_in_map_j).floor() as i32;
         (i, j)
     }
 
+    // Copies of a map position inside visible bounds (several ones when the view is larger than a wrapped map)
+    fn visible_copies(&self, map_position: (i32, i32)) -> Vec<(i32, i32)> {
+        let (i1, j1, i2, j2) = self.visible_map_bounds();
+        let copies = |position: i32, start: i32, end: i32, size: i32, wraps: bool| -> Vec<i32> {
+            if !wraps {
+                return if position >= start && position < end {vec![position]} else {vec![]};
+            }
+            let first = position - (position - start).div_euclid(size) * size;
+            (0..).map(|copy| first + copy * size).take_while(|position| *position < end).collect()
+        };
+        let rows = copies(map_position.0, i1, i2, self.map_height as i32, self.wrap_mode.wraps_vertically());
+        let columns = copies(map_position.1, j1, j2, self.map_width as i32, self.wrap_mode.wraps_horizontally());
+        rows.iter().flat_map(|i| columns.iter().map(move |j| (*i, *j))).collect()
+    }
+
+    // Source rectangles in a map layer texture (one pixel per cell) and their destination rectangles in the window
+    // covering the view, with a piece for each copy of the map along wrapped axes
+    fn map_layer_pieces(&self) -> Vec<([f64; 4], [f64; 4])> {
+        let (cell_pix_width, cell_pix_height) = self.cell_pixel();
+        let rows = axis_pieces(self.view_in_map_i, self.view_in_map_height, self.map_height as f64, self.wrap_mode.wraps_vertically());
+        let columns = axis_pieces(self.view_in_map_j, self.view_in_map_width, self.map_width as f64, self.wrap_mode.wraps_horizontally());
+
+        let mut pieces = Vec::new();
+        for (i, view_i, height) in rows.iter() {
+            for (j, view_j, width) in columns.iter() {
+                pieces.push((
+                    [*j, *i, *width, *height],
+                    [
+                        self.view_in_window_x + view_j * cell_pix_width, self.view_in_window_y + view_i * cell_pix_height, 
+                        width * cell_pix_width, height * cell_pix_height
+                    ]
+                ));
+            }
+        }
+        pieces
+    }
+
+    // Cell a unit is drawn from and its offset in cells, following its move animations behind the game state
+    fn unit_drawn_position(&self, unit: &Rc<RefCell<Unit>>) -> ((usize, usize), (f64, f64)) {
+        match self.animations.unit_move(unit) {
+            Some((Animation::Move {from, to, ..}, progress)) => {
+                let (from, to) = (*from, *to);
+                let map_size = (self.map_height as f64, self.map_width as f64);
+                let (di, dj) = self.wrap_mode.delta((from.0 as f64, from.1 as f64), (to.0 as f64, to.1 as f64), map_size);
+                (from, (di * progress, dj * progress))
+            },
+            _ => (unit.borrow().position, (0.0, 0.0))
+        }
+    }
+
+    // Cells in vision range of a position (across edges on wrapped maps)
+    fn vision_cells(&self, position: (usize, usize)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (-VISION_RANGE..=VISION_RANGE)
+            .flat_map(|di| (-VISION_RANGE..=VISION_RANGE).map(move |dj| (di, dj)))
+            .filter(|(di, dj)| di * di + dj * dj <= VISION_RANGE * VISION_RANGE)
+            .filter_map(move |(di, dj)| self.wrap_map_position((position.0 as i32 + di, position.1 as i32 + dj)))
+            .map(|(i, j)| (i as usize, j as usize))
+    }
+
+    // Positions of the units and buildings of a player, from which it sees the cells around
+    fn player_lookouts(&self, player: usize) -> Vec<(usize, usize)> {
+        let player = &self.players[player];
+        player.units.iter().map(|unit| unit.borrow().position)
+            .chain(player.buildings.iter().map(|building| building.borrow().position))
+            .collect()
+    }
+
+    // Cells seen by a player: around its units and buildings
+    fn player_visibility_map(&self, player: usize) -> Map<bool> {
+        let mut visibility_map = self.new_map(false);
+        for lookout in self.player_lookouts(player) {
+            for cell in self.vision_cells(lookout) {
+                visibility_map[cell] = true;
+            }
+        }
+        visibility_map
+    }
+
+    // Whether a cell is in vision range of one of these unit or building positions
+    fn is_seen_from(&self, lookouts: &[(usize, usize)], cell: (usize, usize)) -> bool {
+        let map_size = (self.map_height as f64, self.map_width as f64);
+        lookouts.iter().any(|lookout| {
+            let (di, dj) = self.wrap_mode.delta((lookout.0 as f64, lookout.1 as f64), (cell.0 as f64, cell.1 as f64), map_size);
+            di * di + dj * dj <= (VISION_RANGE * VISION_RANGE) as f64
+        })
+    }
+
+    // Recompute the cells seen by the active player in hot-seat mode and the fog covering the other ones
+    fn update_visibility(&mut self) {
+        if !self.visibility_outdated {
+            return;
+        }
+        self.visibility_outdated = false;
+        if !self.hot_seat || self.players.is_empty() {
+            self.visibility_map = None;
+            return;
+        }
+
+        let visibility_map = self.player_visibility_map(self.active_player);
+        let image = RgbaImage::from_fn(self.map_width as u32, self.map_height as u32, |x, y| {
+            let position = (y as usize, x as usize);
+            if visibility_map[position] {
+                ::image::Rgba([0, 0, 0, 0])
+            }
+            else {
+                hidden_cell_pixel(self.terrain_color(position))
+            }
+        });
+        update_texture(&mut self.fog_texture, &image);
+        self.visibility_map = Some(visibility_map);
+        self.minimap_outdated = true;
+    }
+
+    // Whether a cell is in sight of the active player (all cells are out of hot-seat mode)
+    fn is_visible(&self, position: (usize, usize)) -> bool {
+        self.visibility_map.as_ref().is_none_or(|visibility_map| visibility_map[position])
+    }
+
+    // Color of a cell in the terrain layer
+    fn terrain_color(&self, position: (usize, usize)) -> [f32; 4] {
+        match self.terrain_rendering {
+            TerrainRendering::Flat => self.terrain_map.borrow()[position].upgrade().map_or(Terrain::default().color, |terrain| terrain.color),
+            TerrainRendering::Relief => shade_color(
+                self.h_to_color(self.height_map[position], true), 
+                self.hillshade_map[position], 
+                self.relief.strength
+            )
+        }
+    }
+
     fn map_position_to_window_position(&self, map_position: (i32, i32)) -> (f64, f64) {
         let (i, j) = map_position;
         let x = ((j as f64 - self.view_in_map_j) / self.view_in_map_width) * self.view_in_window_width + self.view_in_window_x;
@@ -331,16 +848,15 @@ impl<'g> Game<'g> {
         (x, y)
     }
 
+    #[allow(dead_code)]
     fn is_in_window(&self, window_position: (f64, f64)) -> bool {
         let (x, y) = window_position;
-        let window_rect = (0.0, 0.0, self.map_size as f64, self.map_size as f64);
+        let window_rect = (0.0, 0.0, self.map_width as f64, self.map_height as f64);
         is_in_rect((x, y), window_rect, true)
     }
 
-    fn is_in_map(&self, map_position: (i32, i32)) -> bool {
-        let (i, j) = map_position;
-        let map_rect = (0, 0, self.map_size as i32, self.map_size as i32);
-        is_in_rect((i, j), map_rect, false)
+    fn wrap_map_position(&self, map_position: (i32, i32)) -> Option<(i32, i32)> {
+        self.wrap_mode.wrap_position(map_position, (self.map_height as i32, self.map_width as i32))
     }
 
     fn h_to_color(&self, h: f64, interpolate: bool) -> [f32; 4] {
@@ -375,7 +891,7 @@ impl<'g> Game<'g> {
             }   
         }
 
-        return [0., 0., 0., 0.];
+        [0., 0., 0., 0.]
     }
 
     // Gameplay functions
@@ -397,13 +913,40 @@ impl<'g> Game<'g> {
 
     fn turn(&mut self) {
 
-        // Update active player and reset active unit
+        // Keep the view of the player whose turn ends
+        let view = self.view_target.unwrap_or_else(|| self.view());
+        if let Some(player_view) = self.player_views.get_mut(self.active_player) {
+            *player_view = Some(view);
+        }
+
+        // Update active player and reset active unit, a round ends when first player plays again
         self.active_player = (self.active_player + 1) % self.player_num;
+        if self.active_player == 0 {
+            self.turn_num += 1;
+        }
+        self.check_victory();
 
         self.deactivate_active_unit();
+        self.deselect_units();
+        self.pressed_map_cell = None;
+
+        // Restore the view of the new active player, or look at its base on its first turn
+        match self.player_views.get(self.active_player).copied().flatten() {
+            Some(view) => self.animate_view(|game| game.set_view(view)),
+            None => {
+                self.look_at_overview();
+                self.look_at_active_user_base();
+            }
+        }
 
-        // self.look_at_active_user_base();
-        self.look_at_overview();
+        // Hide the map and what happened during the previous turn until the next player is ready
+        if self.hot_seat {
+            self.skip_view_transition();
+            self.animations.clear();
+            self.handover = true;
+            self.visibility_outdated = true;
+            self.minimap_outdated = true;
+        }
 
         // Restore all moves of current active player units
         for player in &self.players {
@@ -412,6 +955,83 @@ impl<'g> Game<'g> {
                 unit.borrow_mut().remaining_moves = speed;
             }
         }
+
+        // Moves made on their own by standing orders are not the new player's to undo
+        self.execute_standing_orders();
+        self.history.clear();
+    }
+
+    // Units of the active player with a goal kept from previous turns move toward it on their own
+    fn execute_standing_orders(&mut self) {
+        let units: Vec<Rc<RefCell<Unit>>> = self.players[self.active_player].units
+            .iter()
+            .filter(|unit| unit.borrow().goal.is_some())
+            .cloned()
+            .collect();
+
+        for unit in units {
+            let position = unit.borrow().position;
+            self.activate_unit(position);
+
+            // Path is replanned from where it was blocked for as long as the unit makes progress
+            loop {
+                let before = (unit.borrow().position, unit.borrow().remaining_moves);
+                self.execute_planned_path();
+                let after = (unit.borrow().position, unit.borrow().remaining_moves);
+                if unit.borrow().goal.is_none() || after == before {
+                    break;
+                }
+            }
+        }
+
+        self.deactivate_active_unit();
+    }
+
+    // Count territory owned by each player over passable cells, and passable cells (terrain or territory reset)
+    fn count_territory(&mut self) {
+        let passable_map = self.passable_map();
+        self.territory_cells_num = vec![0; self.player_num];
+        for (pos, &player) in self.territory_map.iter_positions() {
+            if player < self.player_num && passable_map[pos] {
+                self.territory_cells_num[player] += 1;
+            }
+        }
+        self.passable_cells_num = passable_map.map.iter().filter(|passable| **passable).count();
+    }
+
+    // Check victory conditions and record the winner of the game if there is one
+    fn check_victory(&mut self) {
+        if self.winner.is_some() {
+            return;
+        }
+
+        let (territory, passable_cells_num) = (&self.territory_cells_num, self.passable_cells_num.max(1));
+        let largest_territory_player = (0..self.player_num).max_by_key(|player| territory[*player]);
+
+        for condition in self.victory_conditions.iter() {
+            let winner = match condition {
+                VictoryCondition::Elimination => {
+                    let alive: Vec<usize> = self.players
+                        .iter()
+                        .filter(|player| !player.units.is_empty() || !player.buildings.is_empty())
+                        .map(|player| player.num)
+                        .collect();
+                    if alive.len() == 1 && self.player_num > 1 {Some(alive[0])} else {None}
+                },
+                VictoryCondition::Territory(fraction) => {
+                    (0..self.player_num).find(|player| territory[*player] as f64 / passable_cells_num as f64 >= *fraction)
+                },
+                VictoryCondition::TurnLimit(turns) => {
+                    if self.turn_num >= *turns {largest_territory_player} else {None}
+                }
+            };
+
+            if let Some(winner) = winner {
+                println!("Player {} wins ({:?}) !", winner, condition);
+                self.winner = Some(winner);
+                return;
+            }
+        }
     }
 
     fn deactivate_active_unit(&mut self) {
@@ -419,41 +1039,431 @@ impl<'g> Game<'g> {
         self.active_unit = Weak::new();
     }
 
+    fn deselect_units(&mut self) {
+        self.selected_units.clear();
+        self.group_flow_field = None;
+        self.group_target = None;
+    }
+
+    // Replace the selection with passed units, or add them to it (with the active unit if there is one)
+    fn select_units(&mut self, units: Vec<Weak<RefCell<Unit>>>, add: bool) {
+        let mut selected_units = Vec::new();
+        if add {
+            selected_units.extend(self.selected_units.iter().cloned());
+            selected_units.extend(self.active_unit.upgrade().as_ref().map(Rc::downgrade));
+        }
+        for unit in units {
+            if !selected_units.iter().any(|selected_unit| selected_unit.ptr_eq(&unit)) {
+                selected_units.push(unit);
+            }
+        }
+
+        self.deactivate_active_unit();
+        self.deselect_units();
+        self.selected_units = selected_units;
+    }
+
+    // Add a unit to the selection, or remove it if it was already selected
+    fn toggle_unit_selection(&mut self, pos: (usize, usize)) {
+        let unit = self.unit(pos);
+        if self.selected_units.iter().any(|selected_unit| selected_unit.ptr_eq(&unit)) {
+            self.selected_units.retain(|selected_unit| !selected_unit.ptr_eq(&unit));
+        }
+        else {
+            self.select_units(vec![unit], true);
+        }
+    }
+
+    // Select every unit of the active player as a group
+    fn select_all_units(&mut self) {
+        let units = self.players[self.active_player].units.iter().map(Rc::downgrade).collect();
+        self.select_units(units, false);
+    }
+
+    // Cells of the box between two corner cells, across wrapped edges if the box is smaller this way
+    fn box_cells(&self, corner1: (usize, usize), corner2: (usize, usize)) -> Vec<(i32, i32)> {
+        let map_size = (self.map_height as f64, self.map_width as f64);
+        let (di, dj) = self.wrap_mode.delta((corner1.0 as f64, corner1.1 as f64), (corner2.0 as f64, corner2.1 as f64), map_size);
+        let (i1, j1) = (corner1.0 as i32, corner1.1 as i32);
+        let (i2, j2) = (i1 + di as i32, j1 + dj as i32);
+
+        let mut cells = Vec::new();
+        for i in i1.min(i2)..=i1.max(i2) {
+            for j in j1.min(j2)..=j1.max(j2) {
+                cells.push((i, j));
+            }
+        }
+        cells
+    }
+
+    // Select the active player units in the box between two corner cells
+    fn select_units_in_box(&mut self, corner1: (usize, usize), corner2: (usize, usize), add: bool) {
+        let units = self.box_cells(corner1, corner2)
+            .into_iter()
+            .filter_map(|cell| self.unit_map.borrow().get(cell).and_then(Weak::upgrade))
+            .filter(|unit| unit.borrow().player == self.active_player)
+            .map(|unit| Rc::downgrade(&unit))
+            .collect();
+        self.select_units(units, add);
+    }
+
+    // Store the selection (or the active unit) in a control group
+    fn assign_control_group(&mut self, group: usize) {
+        self.control_groups[group] = if self.selected_units.is_empty() {
+            self.active_unit.upgrade().as_ref().map(Rc::downgrade).into_iter().collect()
+        }
+        else {
+            self.selected_units.clone()
+        };
+    }
+
+    // Select the alive units of a control group, a group of another player is ignored
+    fn recall_control_group(&mut self, group: usize) {
+        let units: Vec<Weak<RefCell<Unit>>> = self.control_groups[group]
+            .iter()
+            .filter(|unit| unit.upgrade().is_some_and(|unit| unit.borrow().player == self.active_player))
+            .cloned()
+            .collect();
+        if !units.is_empty() {
+            self.select_units(units, false);
+        }
+    }
+
+    fn is_there_enemy_on(&self, pos: (usize, usize)) -> bool {
+        let unit_player = self.unit(pos).upgrade().map(|unit| unit.borrow().player);
+        let building_player = self.building(pos).upgrade().map(|building| building.borrow().player);
+        unit_player.or(building_player).is_some_and(|player| player != self.active_player)
+    }
+
+    // Map of cells practicable by a group, buildings being obstacles
+    fn group_passable_map(&self) -> Map<bool> {
+        self.passable_map().zip_with(&self.building_map.borrow(), |passable, building| {
+            *passable && building.upgrade().is_none()
+        })
+    }
+
+    // Cells of a group around a destination, as cheap to reach from it as possible and never shared with other units
+    // (the destination itself is left to the enemy of an attack, and to the building or water on it)
+    fn group_destinations(&self, destination: (usize, usize), units_num: usize, attack: bool, passable_map: &Map<bool>) -> Vec<(i32, i32)> {
+        let destination = (destination.0 as i32, destination.1 as i32);
+        let flow_field = FlowField::new(&[destination], passable_map, &self.move_distance());
+
+        let mut cells: Vec<((i32, i32), f64)> = flow_field.integration_map
+            .iter_positions()
+            .filter(|(_, cost)| cost.is_finite())
+            .map(|((i, j), cost)| ((i as i32, j as i32), *cost))
+            .filter(|(cell, _)| passable_map[(cell.0 as usize, cell.1 as usize)] && !(attack && *cell == destination))
+            .filter(|(cell, _)| {
+                let unit = self.unit((cell.0 as usize, cell.1 as usize));
+                unit.upgrade().is_none() || self.selected_units.iter().any(|selected_unit| selected_unit.ptr_eq(&unit))
+            })
+            .collect();
+        cells.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        cells.into_iter().take(units_num).map(|(cell, _)| cell).collect()
+    }
+
+    // Order selected units to move toward a destination, or to attack the enemy on it, each unit flowing toward the
+    // closest of the group destinations through a flow field integrated once for all of them
+    fn order_group(&mut self, destination: (usize, usize)) {
+        let attack = self.is_there_enemy_on(destination);
+        let units_num = self.selected_units.iter().filter(|unit| unit.upgrade().is_some()).count();
+        let passable_map = self.group_passable_map();
+        let destinations = self.group_destinations(destination, units_num, attack, &passable_map);
+        let flow_field = FlowField::new(&destinations, &passable_map, &self.move_distance());
+
+        let reachable = self.selected_units.iter().filter_map(Weak::upgrade).any(|unit| {
+            let (i, j) = unit.borrow().position;
+            flow_field.cost_to_goal((i as i32, j as i32)).is_finite()
+        });
+        if !reachable {
+            println!("Destination is unreachable by selected units");
+        }
+        self.group_flow_field = Some(flow_field);
+        self.group_target = if attack {Some(destination)} else {None};
+    }
+
+    // Move selected units one cell at a time along the flow field, the ones closest to their destination first
+    // so that followers fill the cells they leave, a unit blocked by another one takes a detour if it can,
+    // units next to the group target attack it once
+    fn execute_group_move(&mut self) {
+        let flow_field = match self.group_flow_field.take() {
+            Some(flow_field) => flow_field,
+            None => return
+        };
+        let unit_constraint = UnitConstraint {
+            unit_map: Rc::downgrade(&self.unit_map)
+        };
+        let building_constraint = BuildingConstraint {
+            building_map: Rc::downgrade(&self.building_map)
+        };
+        self.history.begin();
+        let mut units: Vec<Rc<RefCell<Unit>>> = self.selected_units.iter().filter_map(Weak::upgrade).collect();
+        let mut attackers: Vec<Rc<RefCell<Unit>>> = Vec::new();
+        let cost_to_goal = |unit: &Rc<RefCell<Unit>>| {
+            let (i, j) = unit.borrow().position;
+            flow_field.cost_to_goal((i as i32, j as i32))
+        };
+
+        let mut moved = true;
+        while moved {
+            moved = false;
+            units.sort_by(|a, b| cost_to_goal(a).partial_cmp(&cost_to_goal(b)).unwrap_or(std::cmp::Ordering::Equal));
+            for unit in units.iter() {
+                let (i, j) = unit.borrow().position;
+                let remaining_moves = unit.borrow().remaining_moves;
+
+                // Attack the target from a neighbour cell
+                if let Some(target) = self.group_target {
+                    let target_distance = self.move_distance().evaluate((i as f64, j as f64), (target.0 as f64, target.1 as f64));
+                    let is_neighbour = Neighbourhood::VonNeumann.directions().iter()
+                        .any(|d| self.wrap_map_position((i as i32 + d.0, j as i32 + d.1)) == Some((target.0 as i32, target.1 as i32)));
+                    if is_neighbour && target_distance <= remaining_moves && !attackers.iter().any(|attacker| Rc::ptr_eq(attacker, unit)) {
+                        self.attack(unit, target);
+                        unit.borrow_mut().remaining_moves -= target_distance;
+                        attackers.push(unit.clone());
+                        if !self.is_there_enemy_on(target) {
+                            self.group_target = None;
+                        }
+                        continue;
+                    }
+                }
+
+                let next_position = flow_field
+                    .next_positions((i as i32, j as i32), &self.move_distance())
+                    .into_iter()
+                    .map(|(position, cost)| ((position.0 as usize, position.1 as usize), cost))
+                    .find(|(position, cost)| {
+                        *cost <= remaining_moves && unit_constraint.respect(*position) && building_constraint.respect(*position)
+                    });
+
+                if let Some((position, cost)) = next_position {
+                    self.move_unit(unit, position, cost);
+                    moved = true;
+                }
+            }
+        }
+
+        self.group_flow_field = Some(flow_field);
+    }
+
+    // Damage the enemy unit or building on a position, removing it if it dies
+    fn attack(&mut self, attacker: &Rc<RefCell<Unit>>, target_position: (usize, usize)) {
+        let damage = attacker.borrow().damage;
+        self.animations.push(Animation::Damage {position: target_position, damage});
+        if let Some(unit) = self.unit(target_position).upgrade() {
+            unit.borrow_mut().health -= damage;
+            self.players[unit.borrow().player].purge_dead_units();
+        }
+        else if let Some(building) = self.building(target_position).upgrade() {
+            building.borrow_mut().health -= damage;
+            self.players[building.borrow().player].purge_dead_buildings();
+        }
+        self.path_cache.invalidate(target_position);
+        self.visibility_outdated = true;
+
+        // Attack outcome cannot be taken back, neither can the moves leading to it
+        self.history.record_irreversible();
+    }
+
     fn activate_unit(&mut self, new_active_unit_position: (usize, usize)) {
         // First deactivate active unit if there is one
         self.deactivate_active_unit();
 
         // Activate unit by storing its position
         self.active_unit = self.unit_map.borrow()[new_active_unit_position].clone();
+
+        // Replan path toward the goal kept from previous turns (other units may have moved since)
+        let goal = self.active_unit.upgrade().and_then(|active_unit| active_unit.borrow().goal);
+        if let Some(goal) = goal {
+            self.active_unit_planned_path = self.plan_path(new_active_unit_position, goal);
+        }
+    }
+
+    // Cheapest path avoiding impractical terrains, units and buildings (except on goal, to attack it)
+    fn plan_path(&mut self, start: (usize, usize), goal: (usize, usize)) -> Option<VecDeque<(i32, i32, f64)>> {
+        let map_size = (self.map_height as f64, self.map_width as f64);
+        let distance = WrappedDistance2D {
+            distance: EuclideanDistanceWHeight2D {
+                height_map: &self.height_map
+            },
+            map_size,
+            wrap_mode: self.wrap_mode
+        };
+        let heuristic = WrappedDistance2D {
+            distance: EuclideanDistance2D {},
+            map_size,
+            wrap_mode: self.wrap_mode
+        };
+        let water_constraint = Box::new(TerrainConstraint {
+            terrain_map: Rc::downgrade(&self.terrain_map),
+            impractical_terrains: self.terrains.iter()
+                .filter(|terrain| terrain.impractical)
+                .map(Rc::downgrade)
+                .collect()
+        });
+        let unit_constraint = Box::new(UnitConstraint {
+            unit_map: Rc::downgrade(&self.unit_map)
+        });
+        let building_constraint = Box::new(BuildingConstraint {
+            building_map: Rc::downgrade(&self.building_map)
+        });
+        let way_constraints: Vec<Box<dyn PositionConstraint>> = vec![water_constraint, unit_constraint, building_constraint];
+
+        let (start, goal) = ((start.0 as i32, start.1 as i32), (goal.0 as i32, goal.1 as i32));
+
+        // Long paths of large maps are searched hierarchically
+        if let Some(hierarchical_map) = &self.hierarchical_map {
+            let start_to_goal = heuristic.evaluate((start.0 as f64, start.1 as f64), (goal.0 as f64, goal.1 as f64));
+            if start_to_goal >= HIERARCHICAL_PATH_PLANNING_MIN_DISTANCE {
+                return hierarchical_map.find_path(start, goal, distance, heuristic, way_constraints, vec![]);
+            }
+        }
+
+        let cost = |from: (i32, i32), to: (i32, i32)| {
+            let position = (to.0 as usize, to.1 as usize);
+            if to == goal || way_constraints.iter().all(|constraint| constraint.respect(position)) {
+                distance.evaluate((from.0 as f64, from.1 as f64), (to.0 as f64, to.1 as f64))
+            }
+            else {
+                f64::INFINITY
+            }
+        };
+
+        self.path_cache.find_path(
+            start, 
+            goal, 
+            (self.map_height as i32, self.map_width as i32), 
+            self.wrap_mode, 
+            &cost, 
+            &heuristic
+        )
     }
         
 
     // FIXME : Fix bugs when unit moves, losing track of active unit and its position
     // TODO : Reformat this part of code if possible
-    fn moves(&mut self, destination: (usize, usize)) {
+    fn moves(&mut self, destination: (usize, usize), cost: f64) {
 
         // Get active unit position
         if let Some(active_unit) = self.active_unit.upgrade() {
-            
-            // Make the moves
-            self.unit_map.borrow_mut()[destination] = self.active_unit.clone();
-            self.unit_map.borrow_mut()[active_unit.borrow().position] = Weak::new();
-            
-            // Update active unit position attribute
-            active_unit.borrow_mut().position = destination;
+            self.move_unit(&active_unit, destination, cost);
+        }
+    }
 
-            self.takes_territory(destination);
+    // Move a unit spending its moves and taking the destination territory, recorded to be undone
+    fn move_unit(&mut self, unit: &Rc<RefCell<Unit>>, destination: (usize, usize), cost: f64) {
+        self.history.record(UnitMove {
+            unit: Rc::downgrade(unit),
+            from: unit.borrow().position,
+            to: destination,
+            cost,
+            previous_owner: self.territory_map[destination],
+            previous_goal: unit.borrow().goal
+        });
+        self.animations.push(Animation::Move {unit: Rc::downgrade(unit), from: unit.borrow().position, to: destination});
+
+        // Only cells around the destination can be revealed, fog of war only covering the map in hot-seat mode
+        let reveals = self.hot_seat && {
+            let lookouts = self.player_lookouts(unit.borrow().player);
+            self.vision_cells(destination).any(|cell| !self.is_seen_from(&lookouts, cell))
+        };
+
+        self.place_unit(unit, destination);
+        unit.borrow_mut().remaining_moves -= cost;
+        self.takes_territory(destination);
+
+        // What the move revealed cannot be hidden again, neither can the moves leading to it be taken back
+        if reveals {
+            self.history.record_irreversible();
         }
     }
 
+    fn place_unit(&mut self, unit: &Rc<RefCell<Unit>>, destination: (usize, usize)) {
+
+        // Make the moves
+        self.unit_map.borrow_mut()[destination] = Rc::downgrade(unit);
+        self.unit_map.borrow_mut()[unit.borrow().position] = Weak::new();
+        self.path_cache.invalidate(unit.borrow().position);
+        self.path_cache.invalidate(destination);
+
+        // Update unit position attribute
+        unit.borrow_mut().position = destination;
+        self.visibility_outdated = true;
+    }
+
+    // Revert the moves of the last command of the turn
+    fn undo(&mut self) {
+        self.animations.clear();
+        match self.history.undo() {
+            Some(unit_moves) => {
+                for unit_move in unit_moves.iter().rev() {
+                    if let Some(unit) = unit_move.unit.upgrade() {
+                        self.place_unit(&unit, unit_move.from);
+                        unit.borrow_mut().remaining_moves += unit_move.cost;
+                        unit.borrow_mut().goal = unit_move.previous_goal;
+                        self.set_territory_owner(unit_move.to, unit_move.previous_owner);
+                    }
+                }
+                self.replan_active_unit_path();
+            },
+            None => println!("Nothing to undo")
+        }
+    }
+
+    // Apply again the moves of the last undone command
+    fn redo(&mut self) {
+        self.animations.clear();
+        match self.history.redo() {
+            Some(unit_moves) => {
+                for unit_move in unit_moves.iter() {
+                    if let Some(unit) = unit_move.unit.upgrade() {
+                        self.place_unit(&unit, unit_move.to);
+                        unit.borrow_mut().remaining_moves -= unit_move.cost;
+                        if unit.borrow().goal == Some(unit_move.to) {
+                            unit.borrow_mut().goal = None;
+                        }
+                        self.takes_territory(unit_move.to);
+                    }
+                }
+                self.replan_active_unit_path();
+            },
+            None => println!("Nothing to redo")
+        }
+    }
+
+    fn replan_active_unit_path(&mut self) {
+        let order = self.active_unit.upgrade().and_then(|active_unit| {
+            let active_unit = active_unit.borrow();
+            active_unit.goal.map(|goal| (active_unit.position, goal))
+        });
+        self.active_unit_planned_path = order.and_then(|(position, goal)| self.plan_path(position, goal));
+    }
+
     fn takes_territory(&mut self, territory_position: (usize, usize)) {
-        self.territory_map[territory_position] = self.active_player;
+        self.set_territory_owner(territory_position, self.active_player);
+    }
+
+    // Change the owner of a cell, updating the territory layer and the minimap
+    fn set_territory_owner(&mut self, position: (usize, usize), owner: usize) {
+        let passable = self.terrain_map.borrow()[position].upgrade().is_none_or(|terrain| !terrain.impractical);
+        if passable {
+            if let Some(cells_num) = self.territory_cells_num.get_mut(self.territory_map[position]) {
+                *cells_num -= 1;
+            }
+            if let Some(cells_num) = self.territory_cells_num.get_mut(owner) {
+                *cells_num += 1;
+            }
+        }
+        self.territory_map[position] = owner;
+        self.territory_changes.push(position);
+        self.minimap_outdated = true;
     }
 
     fn execute_planned_path(&mut self) {
         
         // Check if there is an active unit
         if let Some(active_unit) = self.active_unit.upgrade() {
+            self.history.begin();
 
             // If there is an active planned path -> Execute it
             let mut previous_cost = 0.0;
@@ -476,9 +1486,9 @@ impl<'g> Game<'g> {
                             }
                             // Attack
                             else {
-                                unit_on_destination.borrow_mut().health -= active_unit.borrow().damage;
+                                self.attack(&active_unit, current_destination);
                                 active_unit.borrow_mut().remaining_moves -= *cost - previous_cost;
-                                self.players[unit_on_destination.borrow().player].purge_dead_units();
+                                active_unit.borrow_mut().goal = None;
                                 break;
                             }
                         }
@@ -493,16 +1503,14 @@ impl<'g> Game<'g> {
                             }
                             // Attack
                             else {
-                                building_on_destination.borrow_mut().health -= active_unit.borrow().damage;
+                                self.attack(&active_unit, current_destination);
                                 active_unit.borrow_mut().remaining_moves -= *cost - previous_cost;
-                                self.players[building_on_destination.borrow().player].purge_dead_buildings();
+                                active_unit.borrow_mut().goal = None;
                                 break;
                             }
                         }
                         else {
-                            self.moves(current_destination);
-
-                            active_unit.borrow_mut().remaining_moves -= *cost - previous_cost;
+                            self.moves(current_destination, *cost - previous_cost);
                         }
                         
                         previous_cost = *cost;
@@ -510,33 +1518,95 @@ impl<'g> Game<'g> {
                 }
             }
             
-            // Reset active unit planned path
-            self.active_unit_planned_path = None;
+            // Keep planning the rest of the path for next turns until goal is reached
+            let position = active_unit.borrow().position;
+            if active_unit.borrow().goal == Some(position) {
+                active_unit.borrow_mut().goal = None;
+            }
+            let goal = active_unit.borrow().goal;
+            self.active_unit_planned_path = match goal {
+                Some(goal) => self.plan_path(position, goal),
+                None => None
+            };
         }
     }
 
     // View-related functions
-    fn view_center(&self) -> [f64; 2] {
-        [
-            self.view_in_map_i + self.view_in_map_height / 2.0,
-            self.view_in_map_j + self.view_in_map_width / 2.0
-        ]
-    }
-
     fn shift_view(&mut self, shift: (f64, f64)) {
         let frac =  1.0 / 2.0;
-        self.view_in_map_i = 
-            (self.view_in_map_i + shift.0)
-            .max(-self.view_in_map_height * frac)
-            .min(self.map_size as f64 - self.view_in_map_height * (1.0 - frac) - f64::EPSILON);
+
+        // View scrolls endlessly along wrapped axes
+        if self.wrap_mode.wraps_vertically() {
+            self.view_in_map_i = (self.view_in_map_i + shift.0).rem_euclid(self.map_height as f64);
+        }
+        else {
+            self.view_in_map_i = 
+                (self.view_in_map_i + shift.0)
+                .max(-self.view_in_map_height * frac)
+                .min(self.map_height as f64 - self.view_in_map_height * (1.0 - frac) - f64::EPSILON);
+        }
         
-        self.view_in_map_j = 
-            (self.view_in_map_j + shift.1)
-            .max(-self.view_in_map_width * frac)
-            .min(self.map_size as f64 - self.view_in_map_width * (1.0 - frac) - f64::EPSILON);
+        if self.wrap_mode.wraps_horizontally() {
+            self.view_in_map_j = (self.view_in_map_j + shift.1).rem_euclid(self.map_width as f64);
+        }
+        else {
+            self.view_in_map_j = 
+                (self.view_in_map_j + shift.1)
+                .max(-self.view_in_map_width * frac)
+                .min(self.map_width as f64 - self.view_in_map_width * (1.0 - frac) - f64::EPSILON);
+        }
     }
 
-    fn look_at(&mut self, pos: (f64, f64)) {
+    fn view(&self) -> [f64; 4] {
+        [self.view_in_map_i, self.view_in_map_j, self.view_in_map_height, self.view_in_map_width]
+    }
+
+    fn set_view(&mut self, view: [f64; 4]) {
+        self.view_in_map_i = view[0];
+        self.view_in_map_j = view[1];
+        self.view_in_map_height = view[2];
+        self.view_in_map_width = view[3];
+    }
+
+    // Apply a view change through an animated transition, following the one already running if any
+    fn animate_view<F: FnOnce(&mut Self)>(&mut self, change: F) {
+        let current_view = self.view();
+        if let Some(view_target) = self.view_target.take() {
+            self.set_view(view_target);
+        }
+        change(self);
+        self.view_target = Some(self.view());
+        self.set_view(current_view);
+    }
+
+    fn skip_view_transition(&mut self) {
+        if let Some(view_target) = self.view_target.take() {
+            self.set_view(view_target);
+        }
+    }
+
+    // Move view toward the target of the running transition, along the shortest way on wrapped maps
+    fn update_view_transition(&mut self, dt: f64) {
+        if let Some(view_target) = self.view_target {
+            let progress = 1.0 - (-CAMERA_SMOOTHING * dt).exp();
+            let map_size = (self.map_height as f64, self.map_width as f64);
+            let (di, dj) = self.wrap_mode.delta((self.view_in_map_i, self.view_in_map_j), (view_target[0], view_target[1]), map_size);
+            let (dh, dw) = (view_target[2] - self.view_in_map_height, view_target[3] - self.view_in_map_width);
+
+            // Close enough to snap on target (a hundredth of a cell)
+            if [di, dj, dh, dw].iter().all(|d| d.abs() < 0.01) {
+                self.skip_view_transition();
+                return;
+            }
+
+            self.view_in_map_height += dh * progress;
+            self.view_in_map_width += dw * progress;
+            self.shift_view((di * progress, dj * progress));
+        }
+    }
+
+    // Center view on a map position without transition
+    fn jump_to(&mut self, pos: (f64, f64)) {
         
         // Shift view
         self.shift_view(
@@ -547,27 +1617,85 @@ impl<'g> Game<'g> {
         );
     }
 
-    fn zoom(&mut self, scroll_factor: f64) {
-        let view_center = self.view_center();
-        let (view_center_in_map_i, view_center_in_map_j) = (view_center[0], view_center[1]);
+    fn look_at(&mut self, pos: (f64, f64)) {
+        self.animate_view(|game| game.jump_to(pos));
+    }
+
+    // Zoom in (positive scroll) or out (negative scroll) proportionally to scroll delta, keeping the map position
+    // under the anchor window position (view center if None) at the same place
+    fn zoom(&mut self, scroll: f64, anchor: Option<(f64, f64)>) {
+        self.view_target = None;
+        let anchor = anchor.unwrap_or((
+            self.view_in_window_x + self.view_in_window_width / 2.0, 
+            self.view_in_window_y + self.view_in_window_height / 2.0
+        ));
+        let anchor_in_map_i = ((anchor.1 - self.view_in_window_y) / self.view_in_window_height) * self.view_in_map_height + self.view_in_map_i;
+        let anchor_in_map_j = ((anchor.0 - self.view_in_window_x) / self.view_in_window_width) * self.view_in_map_width + self.view_in_map_j;
+
+        // Keep cells square by scaling both view dimensions with the same factor, bounded by the largest map dimension
+        let max_view_size = 2.0 * self.map_width.max(self.map_height) as f64;
+        let view_in_map_height = (self.view_in_map_height * ZOOM_PER_SCROLL_STEP.powf(-scroll)).clamp(3.0, max_view_size);
+        let factor = view_in_map_height / self.view_in_map_height;
+        self.view_in_map_height = view_in_map_height;
+        self.view_in_map_width *= factor;
+
+        self.view_in_map_i = anchor_in_map_i - (anchor_in_map_i - self.view_in_map_i) * factor;
+        self.view_in_map_j = anchor_in_map_j - (anchor_in_map_j - self.view_in_map_j) * factor;
+        self.shift_view((0.0, 0.0));
+    }
+
+    fn look_at_overview(&mut self) {
+        // Fit the largest map dimension in the (square) view
+        let view_size = self.map_width.max(self.map_height) as f64;
+        let center = (self.map_height as f64 / 2.0, self.map_width as f64 / 2.0);
+        self.animate_view(|game| {
+            game.view_in_map_width = view_size;
+            game.view_in_map_height = view_size;
+            game.jump_to(center);
+        });
+    }
 
-        if scroll_factor == 1.0 {
-            self.view_in_map_height = (self.view_in_map_height / 2.0).max(3.0);
-            self.view_in_map_width = (self.view_in_map_width / 2.0).max(3.0);
+    // Pan view with held pan keys (faster and faster) and with the mouse on window borders
+    fn update_panning(&mut self, dt: f64) {
+        let mut direction: (f64, f64) = (0.0, 0.0);
+        for (_, action) in self.held_pan_buttons.iter() {
+            match action {
+                Action::PanUp => direction.0 -= 1.0,
+                Action::PanDown => direction.0 += 1.0,
+                Action::PanLeft => direction.1 -= 1.0,
+                Action::PanRight => direction.1 += 1.0,
+                _ => {}
+            }
         }
-        else if scroll_factor == -1.0 {
-            self.view_in_map_height = (self.view_in_map_height * 2.0).min(2.0 * self.map_size as f64);
-            self.view_in_map_width = (self.view_in_map_width * 2.0).min(2.0 * self.map_size as f64);
+        if self.held_pan_buttons.is_empty() {
+            self.pan_hold_time = 0.0;
+        }
+        else {
+            self.pan_hold_time += dt;
+        }
+
+        if let (true, Some([x, y])) = (self.cursor_in_window, self.current_mouse_position) {
+            if x < EDGE_PAN_MARGIN {direction.1 -= 1.0;}
+            if x > self.window_width - EDGE_PAN_MARGIN {direction.1 += 1.0;}
+            if y < EDGE_PAN_MARGIN {direction.0 -= 1.0;}
+            if y > self.window_height - EDGE_PAN_MARGIN {direction.0 += 1.0;}
         }
 
-        self.view_in_map_i = view_center_in_map_i - self.view_in_map_height / 2.0;
-        self.view_in_map_j = view_center_in_map_j - self.view_in_map_width / 2.0;
+        let direction = (direction.0.clamp(-1.0, 1.0), direction.1.clamp(-1.0, 1.0));
+        if direction != (0.0, 0.0) {
+            self.view_target = None;
+            let speed = (PAN_SPEED + PAN_ACCELERATION * self.pan_hold_time).min(PAN_MAX_SPEED) * dt;
+            self.shift_view((direction.0 * speed * self.view_in_map_height, direction.1 * speed * self.view_in_map_width));
+        }
     }
 
-    fn look_at_overview(&mut self) {
-        self.view_in_map_width = self.map_size as f64;
-        self.view_in_map_height = self.map_size as f64;
-        self.look_at((self.map_size as f64 / 2.0, self.map_size as f64 / 2.0));
+    fn update(&mut self, dt: f64) {
+        if self.handover {
+            return;
+        }
+        self.update_panning(dt);
+        self.update_view_transition(dt);
+        self.animations.update(dt);
     }
 
     fn look_at_cell(&mut self, cell: (usize, usize)) {
@@ -575,195 +1703,295 @@ impl<'g> Game<'g> {
     }
 
     fn look_at_active_user_base(&mut self) {
-        let base_pos = self.players[self.active_player].buildings[0].borrow().position;
-        self.look_at_cell(base_pos);
+        // Look at first building, or first unit of players without building
+        let player = &self.players[self.active_player];
+        let base = player.buildings.first().map(|building| building.borrow().position)
+            .or_else(|| player.units.first().map(|unit| unit.borrow().position));
+        if let Some(base_pos) = base {
+            self.look_at_cell(base_pos);
+        }
     }
 
     // Event and Update methods
     // TODO : Collect differents input and store them in an appropriate structure
     pub fn process_event(&mut self, event: Event) {
 
+        // Handover screen hides the map until the next player presses any button (modifiers are still tracked)
+        if self.handover {
+            if let Some(button) = event.press_args() {
+                self.key_bindings.press(button);
+                self.handover = false;
+            }
+            if let Some(button) = event.release_args() {
+                self.key_bindings.release(button);
+            }
+            if let Some(args) = event.render_args() {
+                self.render(&args);
+            }
+            return;
+        }
+
         // Update the current mouse position and underlying cell (if possible)
+        if let Some(cursor_in_window) = event.cursor_args() {
+            self.cursor_in_window = cursor_in_window;
+        }
+
         if let Some(args) = event.mouse_cursor_args() {
             self.current_mouse_position = Some(args);
 
-            // Get underlying map cell position
+            // Drag panning moves the map with the mouse
+            if let Some(drag_pan_position) = self.drag_pan_position {
+                let (cell_pix_width, cell_pix_height) = self.cell_pixel();
+                self.shift_view(((drag_pan_position[1] - args[1]) / cell_pix_height, (drag_pan_position[0] - args[0]) / cell_pix_width));
+                self.drag_pan_position = Some(args);
+            }
+
+            // Get underlying map cell position (only cells drawn in the view)
             let (mx, my) = (args[0], args[1]);
             let (mi, mj) = self.window_position_to_map_position((mx, my));
+            let view_rect = (
+                self.view_in_window_x, self.view_in_window_y, 
+                self.view_in_window_x + self.view_in_window_width, self.view_in_window_y + self.view_in_window_height
+            );
 
-            if self.is_in_map((mi, mj)) {
-                self.current_underlying_cell = Some([mi as usize, mj as usize]);
+            match self.wrap_map_position((mi, mj)) {
+                Some((mi, mj)) if is_in_rect((mx, my), view_rect, false) => {
+                    self.current_underlying_cell = Some([mi as usize, mj as usize]);
+                },
+                _ => {
+                    self.current_underlying_cell = None;
+                }
             }
-            else {
-                self.current_underlying_cell = None;
+
+            // Get underlying minimap position, the view follows it while dragging on the minimap
+            self.minimap_hovered_position = self.minimap_position_to_map_position((mx, my));
+            if self.minimap_dragging {
+                if let Some(minimap_hovered_position) = self.minimap_hovered_position {
+                    self.view_target = None;
+                    self.jump_to(minimap_hovered_position);
+                }
             }
         }
-        else {
-            self.current_mouse_position = None;
-        }
 
-        // Mouse button pressed
-        if let Some(Button::Mouse(mouse_button)) = event.press_args() {
-            
-            // Left mouse button pressed
-            if mouse_button == MouseButton::Left {
-                
-                // If there is underlying map cell -> Press on
-                if let Some(current_underlying_cell) = self.current_underlying_cell {
-                    self.pressed_map_cell = Some(current_underlying_cell)
-                }
-                else {
-                    self.pressed_map_cell = None;
-                }
+        // Button pressed, mapped to an action
+        let pressed_action = event.press_args().and_then(|button| self.key_bindings.press(button));
+        let released_action = event.release_args().and_then(|button| self.key_bindings.release(button));
+
+        // Pan actions last as long as their button is held, drag panning as long as its button is
+        if let (Some(button), Some(action)) = (event.press_args(), pressed_action) {
+            match action {
+                Action::PanUp | Action::PanDown | Action::PanLeft | Action::PanRight 
+                    if !self.held_pan_buttons.iter().any(|(held_button, _)| *held_button == button) => {
+                    self.held_pan_buttons.push((button, action));
+                },
+                Action::DragPan => {
+                    self.view_target = None;
+                    self.drag_pan_position = self.current_mouse_position;
+                },
+                _ => {}
+            }
+        }
+        if let Some(button) = event.release_args() {
+            self.held_pan_buttons.retain(|(held_button, _)| *held_button != button);
+            if released_action == Some(Action::DragPan) {
+                self.drag_pan_position = None;
             }
         }
 
-        // Mouse button released
-        if let Some(Button::Mouse(mouse_button)) = event.release_args() {
+        // Selection button pressed on the minimap
+        if pressed_action == Some(Action::Select) && self.minimap_hovered_position.is_some() {
+            self.minimap_dragging = true;
+            self.look_at(self.minimap_hovered_position.unwrap());
+        }
+
+        // Selection button pressed
+        else if pressed_action == Some(Action::Select) {
             
-            // Left mouse button released
-            if mouse_button == MouseButton::Left {
+            // If there is underlying map cell -> Press on
+            if let Some(current_underlying_cell) = self.current_underlying_cell {
+                self.pressed_map_cell = Some(current_underlying_cell)
+            }
+            else {
+                self.pressed_map_cell = None;
+            }
+        }
 
-                // If there is underlying map cell -> Release from
-                if let Some(current_underlying_cell) = self.current_underlying_cell {
-                    self.released_map_cell = Some(current_underlying_cell)
-                }
-                else {
-                    self.released_map_cell = None;
-                }
+        // Selection button released after dragging on the minimap
+        if released_action == Some(Action::Select) && self.minimap_dragging {
+            self.minimap_dragging = false;
+        }
 
-                // If pressed map cell and released map cell is equal -> CLICK EVENT
-                if self.pressed_map_cell == self.released_map_cell {
-                    if let Some(released_map_cell) = self.released_map_cell {
-                        let cpos = (released_map_cell[0], released_map_cell[1]);
-                        let (ci, cj) = (cpos.0 as i32, cpos.1 as i32);
-                        
-                        // Check if there is an active unit
-                        if self.active_unit.upgrade().is_some() {
-
-                            // Compute "optimal" path from active unit position to the pointed position
-                            let active_unit_position = self.active_unit.upgrade().unwrap().borrow().position;
-                            let start = (active_unit_position.0 as i32, active_unit_position.1 as i32);
-                            let goal = (ci, cj);
-                            let distance = EuclideanDistanceWHeight2D {
-                                height_map: &self.height_map
-                            };
-                            let heuristic = NullDistance2D {};
-                            let water_constraint = Box::new(TerrainConstraint {
-                                terrain_map: Rc::downgrade(&self.terrain_map),
-                                impractical_terrains: vec![
-                                    Rc::downgrade(&self.terrains[0]),
-                                    Rc::downgrade(&self.terrains[1])
-                                ]
-                            });
-
-                            let unit_constraint = Box::new(
-                                UnitConstraint {
-                                    unit_map: Rc::downgrade(&self.unit_map)
-                                }
-                            );
-                            let building_constraint = Box::new(
-                                BuildingConstraint {
-                                    building_map: Rc::downgrade(&self.building_map)
-                                }
-                            );
+        // Selection button released
+        else if released_action == Some(Action::Select) {
 
-                            let path_res = astar_2d_map(
-                                start, 
-                                goal, 
-                                (self.map_size as i32, self.map_size as i32), 
-                                distance, 
-                                heuristic,
-                                vec![water_constraint, unit_constraint, building_constraint],
-                                vec![],
-                            );
-                            if let Some(path) = path_res {
-                                self.active_unit_planned_path = Some(path);
-                            }
-                            else {
-                                self.active_unit_planned_path = None;
-                                println!("Something wrong with path planning step");
-                            }
+            // If there is underlying map cell -> Release from
+            if let Some(current_underlying_cell) = self.current_underlying_cell {
+                self.released_map_cell = Some(current_underlying_cell)
+            }
+            else {
+                self.released_map_cell = None;
+            }
 
+            // If pressed map cell and released map cell is equal -> CLICK EVENT
+            if self.pressed_map_cell == self.released_map_cell {
+                if let Some(released_map_cell) = self.released_map_cell {
+                    let cpos = (released_map_cell[0], released_map_cell[1]);
+                    
+                    // Shift-click adds an active player unit to the selection (or removes it)
+                    let on_own_unit = self.unit(cpos).upgrade().is_some_and(|unit| unit.borrow().player == self.active_player);
+                    if self.key_bindings.shift_pressed && on_own_unit {
+                        self.toggle_unit_selection(cpos);
+                    }
 
-                            // let d = (released_map_cell[0] as i32 - active_unit_position[0] as i32).abs() + (released_map_cell[1] as i32 - active_unit_position[1] as i32).abs();
-                            // if d <= active_unit.remaining_moves as i32 {
-                            //     // Make the moves
-                            //     self.unit_map[(released_map_cell[0], released_map_cell[1])] = self.unit_map[(active_unit_position[0], active_unit_position[1])];
-                            //     self.unit_map[(active_unit_position[0], active_unit_position[1])] = None;
+                    // Selected group moves toward pointed position or attacks it, unless it is one of the active player units
+                    else if !self.selected_units.is_empty() {
+                        if on_own_unit {
+                            self.deselect_units();
+                        }
+                        else {
+                            self.order_group(cpos);
+                        }
+                    }
 
-                            //     // Update active unit remaining moves and position
-                            //     if let Some(active_unit) = &mut self.unit_map[(released_map_cell[0], released_map_cell[1])] {
-                            //         active_unit.remaining_moves -= d;
-                            //         active_unit.position = released_map_cell;
-                            //         self.territory_map[(released_map_cell[0], released_map_cell[1])] = active_unit.player;
-                            //     }
+                    // Check if there is an active unit
+                    else if self.active_unit.upgrade().is_some() {
 
-                            //     // Update active unit positition
-                            //     self.active_unit_position = Some(released_map_cell);
-                            // }
+                        // Plan path from active unit position to the pointed position, kept across turns
+                        let active_unit = self.active_unit.upgrade().unwrap();
+                        active_unit.borrow_mut().goal = Some(cpos);
+                        let active_unit_position = active_unit.borrow().position;
+                        self.active_unit_planned_path = self.plan_path(active_unit_position, cpos);
+                        if self.active_unit_planned_path.is_none() {
+                            println!("Something wrong with path planning step");
                         }
 
-                        // Activation underlying unit
-                        if self.is_there_unit_on(cpos) {
-                            
-                            if self.unit(cpos).upgrade().unwrap().borrow().player == self.active_player {
-                                self.activate_unit(cpos);
-                                self.active_unit = self.unit_map.borrow()[cpos].clone();
-                            }
-                        }
+
+                        // let d = (released_map_cell[0] as i32 - active_unit_position[0] as i32).abs() + (released_map_cell[1] as i32 - active_unit_position[1] as i32).abs();
+                        // if d <= active_unit.remaining_moves as i32 {
+                        //     // Make the moves
+                        //     self.unit_map[(released_map_cell[0], released_map_cell[1])] = self.unit_map[(active_unit_position[0], active_unit_position[1])];
+                        //     self.unit_map[(active_unit_position[0], active_unit_position[1])] = None;
+
+                        //     // Update active unit remaining moves and position
+                        //     if let Some(active_unit) = &mut self.unit_map[(released_map_cell[0], released_map_cell[1])] {
+                        //         active_unit.remaining_moves -= d;
+                        //         active_unit.position = released_map_cell;
+                        //         self.territory_map[(released_map_cell[0], released_map_cell[1])] = active_unit.player;
+                        //     }
+
+                        //     // Update active unit positition
+                        //     self.active_unit_position = Some(released_map_cell);
+                        // }
+                    }
+
+                    // Activation underlying unit
+                    if on_own_unit && !self.key_bindings.shift_pressed {
+                        self.activate_unit(cpos);
+                        self.active_unit = self.unit_map.borrow()[cpos].clone();
                     }
                 }
+            }
 
-                self.pressed_map_cell = None;
-                self.released_map_cell = None;
+            // Otherwise, if mouse was dragged over the map -> BOX SELECTION EVENT
+            else if let (Some(pressed_map_cell), Some(released_map_cell)) = (self.pressed_map_cell, self.released_map_cell) {
+                self.select_units_in_box(
+                    (pressed_map_cell[0], pressed_map_cell[1]), 
+                    (released_map_cell[0], released_map_cell[1]), 
+                    self.key_bindings.shift_pressed
+                );
             }
+
+            self.pressed_map_cell = None;
+            self.released_map_cell = None;
         }
 
-        // Keyboard button pressed
-        if let Some(Button::Keyboard(key)) = event.press_args() {
-            let view_move_in_view_size_ratio: f64 = 0.1;
-            let view_move_i = self.view_in_map_height * view_move_in_view_size_ratio;
-            let view_move_j = self.view_in_map_width * view_move_in_view_size_ratio;
-            match key {
-                Key::Up | Key::Z => {
-                    self.shift_view((-view_move_i, 0.0));
+        // Other actions are triggered when their button is pressed
+        if let Some(action) = pressed_action {
+            match action {
+                Action::Undo => {
+                    self.undo();
                 },
-                Key::Down | Key::S => {
-                    self.shift_view((view_move_i, 0.0));
+                Action::Redo => {
+                    self.redo();
                 },
-                Key::Left | Key::Q => {
-                    self.shift_view((0.0, -view_move_j));
+                Action::ZoomIn => {
+                    self.zoom(1.0, None);
                 },
-                Key::Right | Key::D => {
-                    self.shift_view((0.0, view_move_j));
+                Action::ZoomOut => {
+                    self.zoom(-1.0, None);
                 },
-                Key::T => {
+                Action::EndTurn => {
                     self.turn();
                 },
-                Key::Space => {
-                    self.execute_planned_path();
+                Action::ExecutePath => {
+                    if self.selected_units.is_empty() {
+                        self.execute_planned_path();
+                    }
+                    else {
+                        self.execute_group_move();
+                    }
+                },
+                Action::SelectAll => {
+                    self.select_all_units();
+                },
+                Action::RecallControlGroup(group) => {
+                    self.recall_control_group(group);
+                },
+                Action::AssignControlGroup(group) => {
+                    self.assign_control_group(group);
                 },
-                Key::R => {
+                Action::Overview => {
                     self.look_at_overview();
                 },
-                Key::B => {
+                Action::LookAtBase => {
                     self.look_at_active_user_base();
                 },
-                Key::G => {
-                    self.generate_map();
+                Action::RegenerateMap => {
+                    if let Err(error) = self.generate_map() {
+                        println!("{}", error);
+                    }
                 },
-                Key::H => {
-                    self.height_map = diamond_square(self.map_size);
-                }
-                _ => {}
+                Action::NextMapPreset => {
+                    self.next_map_preset();
+                    if let Err(error) = self.generate_map() {
+                        println!("{}", error);
+                    }
+                },
+                Action::ToggleTerrainRendering => {
+                    self.terrain_rendering = match self.terrain_rendering {
+                        TerrainRendering::Flat => TerrainRendering::Relief,
+                        TerrainRendering::Relief => TerrainRendering::Flat
+                    };
+                    self.terrain_outdated = true;
+                    self.visibility_outdated = true;
+                },
+                Action::ExportSnapshot => {
+                    // Snapshots show the whole map, which players must not see in hot-seat mode
+                    if self.hot_seat {
+                        println!("Snapshots cannot be exported in hot-seat mode");
+                    }
+                    else {
+                        match export_game(self, Path::new("snapshots"), 8) {
+                            Ok(paths) => println!("Exported {}", paths.join(", ")),
+                            Err(error) => println!("{}", error)
+                        }
+                    }
+                },
+                Action::Select | Action::DragPan | Action::PanUp | Action::PanDown | Action::PanLeft | Action::PanRight => {}
             }
         }
 
-        // Mouse scroll event
+        // Mouse scroll event, zooming around the pointed position
         if let Some(args) = event.mouse_scroll_args() {
             let (_scroll_x, scroll_y) = (args[0], args[1]);
-            self.zoom(scroll_y);
+            let anchor = self.current_mouse_position
+                .filter(|_| self.current_underlying_cell.is_some())
+                .map(|[x, y]| (x, y));
+            self.zoom(scroll_y, anchor);
+        }
+
+        if let Some(args) = event.update_args() {
+            self.update(args.dt);
         }
 
         if let Some(args) = event.render_args() {
@@ -780,37 +2008,42 @@ impl<'g> Game<'g> {
 
         let (view_in_map_i1, view_in_map_j1, view_in_map_i2, view_in_map_j2) = self.visible_map_bounds();
 
-        for i in view_in_map_i1..view_in_map_i2 {
-            let y = self.i_to_y(i);
-            for j in view_in_map_j1..view_in_map_j2 {
-                let x = self.j_to_x(j);
-                let transform = c.transform.trans(x, y);
+        // Draw terrain layer from the cached texture, rebuilt only when outdated
+        if self.terrain_outdated || self.terrain_texture.is_none() {
+            let image = RgbaImage::from_fn(self.map_width as u32, self.map_height as u32, |x, y| {
+                to_rgba(self.terrain_color((y as usize, x as usize)))
+            });
+            update_texture(&mut self.terrain_texture, &image);
+            self.terrain_outdated = false;
+        }
+        let pieces = self.map_layer_pieces();
+        if let Some(texture) = &self.terrain_texture {
+            for (source, destination) in pieces {
+                Image::new().src_rect(source).rect(destination).draw(texture, &draw_state::DrawState::default(), c.transform, self.gl.as_mut().unwrap());
+            }
+        }
 
-                // Draw each grid cell
-                let terrain = &self.terrain_map.borrow()[(i, j)];
-                if let Some(terrain) = terrain.upgrade() {
-                    rectangle(
-                        terrain.color, 
-                        cell, 
-                        transform, 
-                        self.gl.as_mut().unwrap()
-                    );
-                }
-                
+        if self.terrain_rendering == TerrainRendering::Flat && cell_pix_width.min(cell_pix_height) >= DETAILED_SPRITE_MIN_CELL_PIXELS {
+            self.render_terrain_sprites(c);
+        }
+
+        if self.terrain_rendering == TerrainRendering::Relief && cell_pix_width.min(cell_pix_height) >= CONTOUR_MIN_CELL_PIXELS {
+            if let Some(contour_interval) = self.relief.contour_interval {
+                self.render_contour_lines(c, contour_interval);
             }
         }
 
         // Draw pressed grid cells
         if let Some(pressed_map_cell) = self.pressed_map_cell {
-            let x = self.j_to_x(pressed_map_cell[1] as i32);
-            let y = self.i_to_y(pressed_map_cell[0] as i32);
-            let transform = c.transform.trans(x, y);
-            rectangle([1.0, 0.0, 1.0, 1.0], cell, transform, self.gl.as_mut().unwrap());
+            if let Some((i, j)) = self.visible_position((pressed_map_cell[0] as i32, pressed_map_cell[1] as i32)) {
+                let transform = c.transform.trans(self.j_to_x(j), self.i_to_y(i));
+                rectangle([1.0, 0.0, 1.0, 1.0], cell, transform, self.gl.as_mut().unwrap());
+            }
         }
 
         // Draw grid lines
-        let horizontal_line_thickness = (1.0 as f64).max(cell_pix_width / 100.0);
-        let vertical_line_thickness = (1.0 as f64).max(cell_pix_height / 100.0);
+        let horizontal_line_thickness = 1.0_f64.max(cell_pix_width / 100.0);
+        let vertical_line_thickness = 1.0_f64.max(cell_pix_height / 100.0);
 
         let horizontal_line =
             rectangle_by_corners(
@@ -839,6 +2072,92 @@ impl<'g> Game<'g> {
         }
     }
 
+    // Draw tileset sprites of visible cells, then transition sprites of neighbours whose terrain comes after theirs in
+    // terrain data
+    fn render_terrain_sprites(&mut self, c: Context) {
+        let (tileset, texture) = match (&self.tileset, &self.tileset_texture) {
+            (Some(tileset), Some(texture)) => (tileset, texture),
+            _ => return
+        };
+        if tileset.terrains.is_empty() && tileset.transitions.is_empty() {
+            return;
+        }
+
+        let terrain_indices: HashMap<&str, usize> = self.terrains.iter().enumerate().map(|(index, terrain)| (terrain.name.as_str(), index)).collect();
+        let terrain_map = self.terrain_map.borrow();
+        let (cell_pix_width, cell_pix_height) = self.cell_pixel();
+        let (view_in_map_i1, view_in_map_j1, view_in_map_i2, view_in_map_j2) = self.visible_map_bounds();
+
+        // Cell and sprite rectangles, drawn at once since they all come from the atlas
+        let mut sprites: Vec<([f64; 4], [f64; 4])> = Vec::new();
+        for i in view_in_map_i1..view_in_map_i2 {
+            let y = self.i_to_y(i);
+            for j in view_in_map_j1..view_in_map_j2 {
+                let x = self.j_to_x(j);
+                let terrain = match terrain_map[(i, j)].upgrade() {
+                    Some(terrain) => terrain,
+                    None => continue
+                };
+                let cell = [x, y, cell_pix_width, cell_pix_height];
+
+                if let Some(sprite) = tileset.terrains.get(&terrain.name) {
+                    sprites.push((cell, Tileset::source_rectangle(*sprite)));
+                }
+
+                // Neighbours above, right, below and left, in the order of transition sprites
+                let terrain_index = terrain_indices.get(terrain.name.as_str());
+                for (direction, (di, dj)) in [(-1, 0), (0, 1), (1, 0), (0, -1)].iter().enumerate() {
+                    if let Some(neighbour) = terrain_map.get((i + di, j + dj)).and_then(Weak::upgrade) {
+                        if terrain_indices.get(neighbour.name.as_str()) <= terrain_index {
+                            continue;
+                        }
+                        if let Some(transition_sprites) = tileset.transitions.get(&neighbour.name) {
+                            sprites.push((cell, Tileset::source_rectangle(transition_sprites[direction])));
+                        }
+                    }
+                }
+            }
+        }
+
+        graphics::image::draw_many(
+            &sprites,
+            [1.0, 1.0, 1.0, 1.0],
+            texture,
+            &draw_state::DrawState::default(),
+            c.transform,
+            self.gl.as_mut().unwrap()
+        );
+    }
+
+    // Draw a line on the border between two cells whose heights are in different contour intervals
+    fn render_contour_lines(&mut self, c: Context, contour_interval: f64) {
+        let (cell_pix_width, cell_pix_height) = self.cell_pixel();
+        let line_thickness = 1.0_f64.max(cell_pix_width.min(cell_pix_height) / 10.0);
+        let right_border = rectangle_by_corners(cell_pix_width - line_thickness / 2.0, 0.0, cell_pix_width + line_thickness / 2.0, cell_pix_height);
+        let bottom_border = rectangle_by_corners(0.0, cell_pix_height - line_thickness / 2.0, cell_pix_width, cell_pix_height + line_thickness / 2.0);
+
+        let (view_in_map_i1, view_in_map_j1, view_in_map_i2, view_in_map_j2) = self.visible_map_bounds();
+
+        for i in view_in_map_i1..view_in_map_i2 {
+            let y = self.i_to_y(i);
+            for j in view_in_map_j1..view_in_map_j2 {
+                let x = self.j_to_x(j);
+                let transform = c.transform.trans(x, y);
+
+                let level = contour_level(self.height_map[(i, j)], contour_interval);
+                let right = self.height_map.get((i, j + 1));
+                let bottom = self.height_map.get((i + 1, j));
+                for (neighbour_height, border) in [(right, right_border), (bottom, bottom_border)] {
+                    if let Some(&neighbour_height) = neighbour_height {
+                        if contour_level(neighbour_height, contour_interval) != level {
+                            rectangle(self.relief.contour_color, border, transform, self.gl.as_mut().unwrap());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     // TODO : Adapt this old function to draw shortest-path tree provided by future Dijkstra implementation
     // fn render_unit_reachable_cells(&mut self, c: Context) {
     //     // Draw reachable mask at reachable cells by active unit if there is an active one
@@ -891,19 +2210,17 @@ impl<'g> Game<'g> {
                     round: false
                 };
 
-                let visible_map_bounds = self.visible_map_bounds();
-                
                 let mut max_cost_in_turn = 0.0;
                 for (i, j, cost) in path.iter().skip(1) {
-                    if is_in_rect((*i, *j), visible_map_bounds, false) {
-                        let (x, y) = self.map_position_to_window_position((*i, *j));
+                    if let Some(visible_position) = self.visible_position((*i, *j)) {
+                        let (x, y) = self.map_position_to_window_position(visible_position);
                         let transform = c.transform.trans(x, y);
                         if (*cost - active_unit.borrow().remaining_moves) < 0.0 {
                             max_cost_in_turn = *cost;
                             rectangle([0.0, 1.0, 0.0, 0.3], reachable_cell, transform, self.gl.as_mut().unwrap());
                         }
                         else {
-                            let turn_to_arrive = ((*cost - max_cost_in_turn) / active_unit.borrow().speed as f64).ceil();
+                            let turn_to_arrive = ((*cost - max_cost_in_turn) / active_unit.borrow().speed).ceil();
                             rectangle([1.0, 0.0, 0.0, 0.3], reachable_cell, transform, self.gl.as_mut().unwrap());
                             
                             let turn_to_arrive_str = turn_to_arrive.to_string();
@@ -943,7 +2260,6 @@ impl<'g> Game<'g> {
     fn render_buildings(&mut self, c: Context) {
         // Compute cell dimensions in pixel
         let (cell_pix_width, cell_pix_height) = self.cell_pixel();
-        let (view_in_map_i1, view_in_map_j1, view_in_map_i2, view_in_map_j2) = self.visible_map_bounds();
 
         let cell_padding_ratio = 1.0 / 4.0;
         let building_pix_width = cell_pix_width * (1.0 - cell_padding_ratio * 2.0);
@@ -964,34 +2280,60 @@ impl<'g> Game<'g> {
             )
         };
 
-        // Draw units
-        for i in view_in_map_i1..view_in_map_i2 {
-            for j in view_in_map_j1..view_in_map_j2 {
-                if let Some(building) = self.building_map.borrow()[(i, j)].upgrade() {
-                    building_rectangle
-                        .color(self.players[building.borrow().player].principal_color)
-                        .border(
-                            graphics::rectangle::Border {
-                                color: self.players[building.borrow().player].secondary_color,
-                                radius: (building_pix_width / 2.0) * border_padding_ratio
-                            }
-                        )
-                        .draw(
-                            rectangle, 
-                            &draw_state::DrawState::default(), 
-                            c.transform.trans(self.j_to_x(j), self.i_to_y(i)), 
-                            self.gl.as_mut().unwrap()
-                        );
-                }
+        // Visible buildings, found from player buildings rather than visible cells
+        let buildings: Vec<((i32, i32), usize)> = self.players
+            .iter()
+            .flat_map(|player| player.buildings.iter())
+            .flat_map(|building| {
+                let (position, player) = (building.borrow().position, building.borrow().player);
+                self.visible_copies((position.0 as i32, position.1 as i32)).into_iter().map(move |copy| (copy, player))
+            })
+            .collect();
+
+        // Plain squares batched by color when zoomed out
+        if cell_pix_width.min(cell_pix_height) < DETAILED_SPRITE_MIN_CELL_PIXELS {
+            let rectangles: Vec<([f32; 4], [f64; 4])> = buildings
+                .iter()
+                .map(|(position, player)| {
+                    let (x, y) = self.map_position_to_window_position(*position);
+                    (self.players[*player].principal_color, [x + rectangle[0], y + rectangle[1], rectangle[2], rectangle[3]])
+                })
+                .collect();
+            self.render_batched_rectangles(c, &rectangles);
+            return;
+        }
+
+        // Draw buildings, with their sprite tinted by player color if there is one
+        let building_sprite = self.tileset.as_ref().and_then(|tileset| tileset.building);
+        for ((i, j), player) in buildings {
+            if let (Some(sprite), Some(texture)) = (building_sprite, &self.tileset_texture) {
+                Image::new_color(self.players[player].principal_color)
+                    .src_rect(Tileset::source_rectangle(sprite))
+                    .rect([self.j_to_x(j), self.i_to_y(i), cell_pix_width, cell_pix_height])
+                    .draw(texture, &draw_state::DrawState::default(), c.transform, self.gl.as_mut().unwrap());
+                continue;
             }
+
+            building_rectangle
+                .color(self.players[player].principal_color)
+                .border(
+                    graphics::rectangle::Border {
+                        color: self.players[player].secondary_color,
+                        radius: (building_pix_width / 2.0) * border_padding_ratio
+                    }
+                )
+                .draw(
+                    rectangle, 
+                    &draw_state::DrawState::default(), 
+                    c.transform.trans(self.j_to_x(j), self.i_to_y(i)), 
+                    self.gl.as_mut().unwrap()
+                );
         }
     }
 
     fn render_units(&mut self, c: Context) {
-        // Compute cell dimensions in pixel and compute visible map bounds
+        // Compute cell dimensions in pixel
         let (cell_pix_width, cell_pix_height) = self.cell_pixel();
-        let visible_map_bounds = self.visible_map_bounds();
-        let (view_in_map_i1, view_in_map_j1, view_in_map_i2, view_in_map_j2) = visible_map_bounds;
 
         let cell_padding_ratio = 1.0 / 4.0;
         let unit_pix_width = cell_pix_width * (1.0 - cell_padding_ratio * 2.0);
@@ -1011,33 +2353,84 @@ impl<'g> Game<'g> {
             ),
             resolution: 32
         };
+        let standing_order_color = [1.0, 1.0, 0.0, 1.0];
+        let flag_size = cell_pix_width * cell_padding_ratio * 0.8;
+        let flag_rectangle = [cell_pix_width * (1.0 - cell_padding_ratio), cell_pix_height * cell_padding_ratio - flag_size, flag_size, flag_size];
+
+        // Visible units, found from player units rather than visible cells
+        let units: Vec<DrawnUnit> = self.players
+            .iter()
+            .flat_map(|player| player.units.iter())
+            .flat_map(|unit| {
+                let (position, offset) = self.unit_drawn_position(unit);
+                let (player, has_goal) = (unit.borrow().player, unit.borrow().goal.is_some());
+                self.visible_copies((position.0 as i32, position.1 as i32)).into_iter().map(move |copy| (copy, offset, player, has_goal))
+            })
+            .collect();
+
+        // Plain squares (and flags) batched by color when zoomed out
+        if cell_pix_width.min(cell_pix_height) < DETAILED_SPRITE_MIN_CELL_PIXELS {
+            let mut rectangles: Vec<([f32; 4], [f64; 4])> = Vec::new();
+            for (position, offset, player, has_goal) in units.iter() {
+                let (x, y) = self.map_position_to_window_position(*position);
+                let (x, y) = (x + offset.1 * cell_pix_width, y + offset.0 * cell_pix_height);
+                rectangles.push((self.players[*player].principal_color, [x + rectangle[0], y + rectangle[1], rectangle[2], rectangle[3]]));
+                if *has_goal {
+                    rectangles.push((standing_order_color, [x + flag_rectangle[0], y + flag_rectangle[1], flag_rectangle[2], flag_rectangle[3]]));
+                }
+            }
+            self.render_batched_rectangles(c, &rectangles);
+        }
+        else {
+            // Draw units, with their sprite tinted by player color if there is one
+            let unit_sprite = self.tileset.as_ref().and_then(|tileset| tileset.unit);
+            for (position, offset, player, has_goal) in units.iter() {
+                let (x, y) = self.map_position_to_window_position(*position);
+                let (x, y) = (x + offset.1 * cell_pix_width, y + offset.0 * cell_pix_height);
+
+                // Flag on units with a standing order
+                if *has_goal {
+                    graphics::rectangle(
+                        standing_order_color, 
+                        flag_rectangle, 
+                        c.transform.trans(x, y), 
+                        self.gl.as_mut().unwrap()
+                    );
+                }
 
-        // Draw units
-        for i in view_in_map_i1..view_in_map_i2 {
-            for j in view_in_map_j1..view_in_map_j2 {
-                if let Some(unit) = self.unit_map.borrow()[(i, j)].upgrade() {
-                    let (x, y) = self.map_position_to_window_position((i, j));
-                    unit_ellipse
-                        .color(self.players[unit.borrow().player].principal_color)
-                        .border(
-                            graphics::ellipse::Border {
-                                color: self.players[unit.borrow().player].secondary_color,
-                                radius: (unit_pix_width / 2.0) * border_padding_ratio
-                            }
-                        )
-                        .draw(
-                            rectangle, 
-                            &draw_state::DrawState::default(), 
-                            c.transform.trans(x, y), 
-                            self.gl.as_mut().unwrap()
-                        );
+                if let (Some(sprite), Some(texture)) = (unit_sprite, &self.tileset_texture) {
+                    Image::new_color(self.players[*player].principal_color)
+                        .src_rect(Tileset::source_rectangle(sprite))
+                        .rect([x, y, cell_pix_width, cell_pix_height])
+                        .draw(texture, &draw_state::DrawState::default(), c.transform, self.gl.as_mut().unwrap());
+                    continue;
                 }
+
+                unit_ellipse
+                    .color(self.players[*player].principal_color)
+                    .border(
+                        graphics::ellipse::Border {
+                            color: self.players[*player].secondary_color,
+                            radius: (unit_pix_width / 2.0) * border_padding_ratio
+                        }
+                    )
+                    .draw(
+                        rectangle, 
+                        &draw_state::DrawState::default(), 
+                        c.transform.trans(x, y), 
+                        self.gl.as_mut().unwrap()
+                    );
             }
         }
 
-        // Draw marker on active unit if there is one and if it is visible
-        if let Some(active_unit) = self.active_unit.upgrade() {
-            if is_in_rect((active_unit.borrow().position.0 as i32, active_unit.borrow().position.1 as i32), visible_map_bounds, false) {
+        // Draw marker on active unit and selected units if there are some and if they are visible
+        let marked_units: Vec<Rc<RefCell<Unit>>> = self.active_unit.upgrade()
+            .into_iter()
+            .chain(self.selected_units.iter().filter_map(Weak::upgrade))
+            .collect();
+        for marked_unit in marked_units {
+            let (marked_unit_position, offset) = self.unit_drawn_position(&marked_unit);
+            if let Some(visible_position) = self.visible_position((marked_unit_position.0 as i32, marked_unit_position.1 as i32)) {
                 let cell_padding_ratio = 1.0 / 2.5;
                 let marker_pix_width = cell_pix_width * (1.0 - cell_padding_ratio * 2.0);
                 let marker_pix_height = cell_pix_height * (1.0 - cell_padding_ratio * 2.0);
@@ -1047,19 +2440,15 @@ impl<'g> Game<'g> {
                     marker_pix_width, 
                     marker_pix_height
                 ];
-                let active_unit_marker = Ellipse {
+                let unit_marker = Ellipse {
                     color: [0.0, 0.0, 0.0, 1.0],
                     border: None,
                     resolution: 32
                 };
-                let (x, y) = self.map_position_to_window_position(
-                    (
-                        active_unit.borrow().position.0 as i32, 
-                        active_unit.borrow().position.1 as i32
-                    )
-                );
+                let (x, y) = self.map_position_to_window_position(visible_position);
+                let (x, y) = (x + offset.1 * cell_pix_width, y + offset.0 * cell_pix_height);
 
-                active_unit_marker.draw(
+                unit_marker.draw(
                     rectangle, 
                     &draw_state::DrawState::default(), 
                     c.transform.trans(x, y), 
@@ -1069,46 +2458,162 @@ impl<'g> Game<'g> {
         }
     }
 
-    fn render_territory(&mut self, c: Context) {
-        // Compute cell dimensions in pixel
-        let (_cell_pix_width, cell_pix_height) = self.cell_pixel();
-        let (view_in_map_i1, view_in_map_j1, view_in_map_i2, view_in_map_j2) = self.visible_map_bounds();
-        let font_size = (cell_pix_height / 4.0).floor() as u32;
+    // Draw the flash and the floating damage number of the attack being shown
+    fn render_damage_animation(&mut self, c: Context) {
+        let (position, damage, progress) = match self.animations.current() {
+            Some((Animation::Damage {position, damage}, progress)) => (*position, *damage, progress),
+            _ => return
+        };
+        let (cell_pix_width, cell_pix_height) = self.cell_pixel();
+        let fading = (1.0 - progress) as f32;
+        let font_size = (cell_pix_height / 2.0).max(HUD_FONT_SIZE as f64) as u32;
+        let damage_text = format!("-{:.1}", damage);
+
+        for copy in self.visible_copies((position.0 as i32, position.1 as i32)) {
+            let (x, y) = self.map_position_to_window_position(copy);
+            graphics::rectangle([1.0, 1.0, 1.0, 0.6 * fading], [x, y, cell_pix_width, cell_pix_height], c.transform, self.gl.as_mut().unwrap());
+
+            // Number rising above the cell
+            let text_y = y - progress * cell_pix_height;
+            let draw_res = Text::new_color([1.0, 0.2, 0.2, fading], font_size).draw(
+                damage_text.as_str(), 
+                self.glyphs.as_mut().unwrap(), 
+                &draw_state::DrawState::default(), 
+                c.transform.trans(x, text_y), 
+                self.gl.as_mut().unwrap()
+            );
+            if let Err(_error) = draw_res {
+                dbg!("Something went wrong when drawing damage !");
+            }
+        }
+    }
 
-        for i in view_in_map_i1..view_in_map_i2 {
-            for j in view_in_map_j1..view_in_map_j2 {
-                let player = self.territory_map[(i, j)];
-                if player < self.player_num {
-                    // Get principal and secondary colors for drawing player text
-                    let principal_color = self.players[player].principal_color;
-                    let _secondary_color = self.players[player].secondary_color;
-                    let player_text = Text {
-                        color: principal_color,
-                        font_size: font_size,
-                        round: false
-                    };
+    // Cover the cells out of sight of the active player
+    fn render_fog(&mut self, c: Context) {
+        if self.visibility_map.is_none() {
+            return;
+        }
+        let pieces = self.map_layer_pieces();
+        if let Some(texture) = &self.fog_texture {
+            for (source, destination) in pieces {
+                Image::new().src_rect(source).rect(destination).draw(texture, &draw_state::DrawState::default(), c.transform, self.gl.as_mut().unwrap());
+            }
+        }
+    }
 
-                    let (x, y) = self.map_position_to_window_position((i, j));
+    // Screen hiding the map between two turns of hot-seat mode
+    fn render_handover(&mut self, c: Context) {
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let player_color = self.players.get(self.active_player).map_or(white, |player| player.principal_color);
+        let lines = [
+            (format!("Player {}'s turn", self.active_player), player_color, HANDOVER_FONT_SIZE),
+            (String::from("Press any key when ready"), white, HUD_FONT_SIZE)
+        ];
 
-                    let _draw_res = player_text.draw(
-                        player.to_string().as_str(), 
-                        self.glyphs.as_mut().unwrap(),
-                        &draw_state::DrawState::default(), 
-                        c.transform.trans(x, y + font_size as f64),
-                        self.gl.as_mut().unwrap()
-                    );
+        let mut y = self.window_height / 2.0;
+        for (line, color, font_size) in lines.iter() {
+            let width = self.glyphs.as_mut().unwrap().width(*font_size, line).unwrap_or(0.0);
+            let draw_res = Text::new_color(*color, *font_size).draw(
+                line.as_str(), 
+                self.glyphs.as_mut().unwrap(), 
+                &draw_state::DrawState::default(), 
+                c.transform.trans((self.window_width - width) / 2.0, y), 
+                self.gl.as_mut().unwrap()
+            );
+            if let Err(_error) = draw_res {
+                dbg!("Something went wrong when drawing handover screen !");
+            }
+            y += *font_size as f64 * 2.0;
+        }
+    }
+
+    // Draw rectangles with as few draw calls as possible, batching them by color
+    fn render_batched_rectangles(&mut self, c: Context, rectangles: &[([f32; 4], [f64; 4])]) {
+        let mut batches: Vec<([f32; 4], Vec<[f64; 4]>)> = Vec::new();
+        for (color, rectangle) in rectangles.iter() {
+            match batches.iter_mut().find(|(batch_color, _)| batch_color == color) {
+                Some((_, batch)) => batch.push(*rectangle),
+                None => batches.push((*color, vec![*rectangle]))
+            }
+        }
+
+        let gl = self.gl.as_mut().unwrap();
+        for (color, batch) in batches.iter() {
+            gl.tri_list(&draw_state::DrawState::default(), color, |f| {
+                for chunk in batch.chunks(BACK_END_MAX_VERTEX_COUNT / 6) {
+                    let vertices: Vec<[f32; 2]> = chunk
+                        .iter()
+                        .flat_map(|rectangle| triangulation::rect_tri_list_xy(c.transform, *rectangle))
+                        .collect();
+                    f(&vertices);
+                }
+            });
+        }
+    }
+
+    fn render_selection_box(&mut self, c: Context) {
+        // Box dragged from pressed cell to the cell under the mouse
+        if let (Some(pressed_map_cell), Some(current_underlying_cell)) = (self.pressed_map_cell, self.current_underlying_cell) {
+            if pressed_map_cell == current_underlying_cell {
+                return;
+            }
+
+            let cells = self.box_cells((pressed_map_cell[0], pressed_map_cell[1]), (current_underlying_cell[0], current_underlying_cell[1]));
+            let (first_cell, last_cell) = (cells[0], cells[cells.len() - 1]);
+            if let Some(visible_position) = self.visible_position(first_cell) {
+                let (cell_pix_width, cell_pix_height) = self.cell_pixel();
+                let (x, y) = self.map_position_to_window_position(visible_position);
+                let box_rectangle = [
+                    x, y, 
+                    (last_cell.1 - first_cell.1 + 1) as f64 * cell_pix_width, 
+                    (last_cell.0 - first_cell.0 + 1) as f64 * cell_pix_height
+                ];
+                Rectangle::new_border([1.0, 1.0, 1.0, 1.0], 1.0).draw(
+                    box_rectangle, 
+                    &draw_state::DrawState::default(), 
+                    c.transform, 
+                    self.gl.as_mut().unwrap()
+                );
+            }
+        }
+    }
+
+    fn render_territory(&mut self, c: Context) {
+        // Territory layer from the cached texture, rebuilt if there is no image or updated with the cells whose owner changed
+        if self.territory_image.is_none() {
+            let image = territory_map_image(&self.territory_map, &self.players);
+            update_texture(&mut self.territory_texture, &image);
+            self.territory_image = Some(image);
+            self.territory_changes.clear();
+        }
+        else if !self.territory_changes.is_empty() {
+            if let Some(image) = &mut self.territory_image {
+                for (i, j) in self.territory_changes.drain(..) {
+                    image.put_pixel(j as u32, i as u32, territory_pixel(self.territory_map[(i, j)], &self.players));
                 }
+                update_texture(&mut self.territory_texture, image);
+            }
+        }
+
+        // Player colors painted over terrain
+        let pieces = self.map_layer_pieces();
+        if let Some(texture) = &self.territory_texture {
+            for (source, destination) in pieces {
+                Image::new_color([1.0, 1.0, 1.0, TERRITORY_OVERLAY_ALPHA])
+                    .src_rect(source)
+                    .rect(destination)
+                    .draw(texture, &draw_state::DrawState::default(), c.transform, self.gl.as_mut().unwrap());
             }
         }
 
         // TODO : Implement algorithm to draw territory borders
         // Initialize a processed/visited map
-        // let mut processed_map = Map::<bool>::new(self.map_size, self.map_size, false);
+        // let mut processed_map = Map::<bool>::new(self.map_width, self.map_height, false);
 
         // // Contour generation
         // let mut contours_positions: Vec<Vec<(i32, i32)>> = Vec::new();
-        // for i in 0..self.map_size {
-        //     for j in 0..self.map_size {
+        // for i in 0..self.map_height {
+        //     for j in 0..self.map_width {
         //         let player = self.territory_map[(i, j)];
         //         if player < self.player_num && !processed_map[(i, j)] {
         //             // Launch contour detection from this point
@@ -1138,7 +2643,7 @@ impl<'g> Game<'g> {
 
         //                 // Look to the right
         //                 let right_position = ((current_position.0 + right_direction.0), (current_position.1 + right_direction.1));
-        //                 if right_position.0 >= 0 && right_position.0 < self.map_size as i32 && right_position.1 >= 0 && right_position.1 < self.map_size as i32 {
+        //                 if right_position.0 >= 0 && right_position.0 < self.map_height as i32 && right_position.1 >= 0 && right_position.1 < self.map_width as i32 {
         //                     if self.territory_map[right_position] == player {
         //                         // Update current position and direction
         //                         current_position = right_position;
@@ -1158,7 +2663,7 @@ impl<'g> Game<'g> {
 
         //                     // Right impossible --> Look ahead
         //                     let ahead_position = ((current_position.0 + current_direction.0), (current_position.1 + current_direction.1));
-        //                     if ahead_position.0 >= 0 && ahead_position.0 < self.map_size as i32 && ahead_position.1 >= 0 && ahead_position.1 < self.map_size as i32 {
+        //                     if ahead_position.0 >= 0 && ahead_position.0 < self.map_height as i32 && ahead_position.1 >= 0 && ahead_position.1 < self.map_width as i32 {
         //                         if self.territory_map[ahead_position] == player {
         //                             // Update current position (direction doesn't change)
         //                             current_position = ahead_position;
@@ -1178,7 +2683,7 @@ impl<'g> Game<'g> {
 
         //                     // Ahead impossible --> Look to the left
         //                     let left_position = ((current_position.0 + left_direction.0), (current_position.1 + left_direction.1));
-        //                     if left_position.0 >= 0 && left_position.0 < self.map_size as i32 && left_position.1 >= 0 && left_position.1 < self.map_size as i32 {
+        //                     if left_position.0 >= 0 && left_position.0 < self.map_height as i32 && left_position.1 >= 0 && left_position.1 < self.map_width as i32 {
         //                         if self.territory_map[left_position] == player {
         //                             // Update current position and direction
         //                             current_position = left_position;
@@ -1199,7 +2704,7 @@ impl<'g> Game<'g> {
 
         //                     // Look behind
         //                     let behind_position = ((current_position.0 + behind_direction.0), (current_position.1 + behind_direction.1));
-        //                     if behind_position.0 >= 0 && behind_position.0 < self.map_size as i32 && behind_position.1 >= 0 && behind_position.1 < self.map_size as i32 {
+        //                     if behind_position.0 >= 0 && behind_position.0 < self.map_height as i32 && behind_position.1 >= 0 && behind_position.1 < self.map_width as i32 {
         //                         if self.territory_map[behind_position] == player {
         //                             // Update current position and direction
         //                             current_position = behind_position;
@@ -1278,7 +2783,218 @@ impl<'g> Game<'g> {
         // }
     }
 
+    // HUD panel rectangle right of the view, with the same margins as the view
+    fn hud_rectangle(&self) -> [f64; 4] {
+        let margin = self.view_in_window_x;
+        let x = self.view_in_window_x + self.view_in_window_width + margin;
+        [x, self.view_in_window_y, self.window_width - x - margin, self.view_in_window_height]
+    }
+
+    // HUD text lines with their colors: turn, active player, selection, hovered cell and territories
+    fn hud_lines(&self) -> Vec<(String, [f32; 4])> {
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let gray = [0.7, 0.7, 0.7, 1.0];
+        let player_color = |player: usize| self.players.get(player).map_or(gray, |player| player.principal_color);
+        let mut lines = Vec::new();
+
+        // Turn and active player
+        lines.push((format!("Turn {}", self.turn_num + 1), white));
+        lines.push((format!("Player {} to play", self.active_player), player_color(self.active_player)));
+        if let Some(winner) = self.winner {
+            lines.push((format!("Player {} won", winner), player_color(winner)));
+        }
+        if self.history.can_undo() {
+            lines.push((String::from("Last command can be undone"), gray));
+        }
+        if self.history.can_redo() {
+            lines.push((String::from("Undone command can be redone"), gray));
+        }
+        let map_source = self.height_map_path.as_ref().unwrap_or(&self.map_preset);
+        lines.push((format!("Map {} (seed {})", map_source, self.current_map_seed), gray));
+        lines.push((String::new(), white));
+
+        // Selected unit or group
+        let selected_units: Vec<Rc<RefCell<Unit>>> = self.active_unit.upgrade()
+            .into_iter()
+            .chain(self.selected_units.iter().filter_map(Weak::upgrade))
+            .collect();
+        match selected_units.len() {
+            0 => lines.push((String::from("No unit selected"), gray)),
+            1 => {
+                let unit = selected_units[0].borrow();
+                lines.push((format!("Unit at {:?}", unit.position), white));
+                lines.push((format!("  Health {:.1}", unit.health), white));
+                lines.push((format!("  Damage {:.1}", unit.damage), white));
+                lines.push((format!("  Moves {:.1} / {:.1}", unit.remaining_moves, unit.speed), white));
+                if let Some(goal) = unit.goal {
+                    lines.push((format!("  Moving to {:?}", goal), white));
+                }
+            },
+            units_num => {
+                let remaining_moves = selected_units.iter().map(|unit| unit.borrow().remaining_moves).fold(f64::INFINITY, f64::min);
+                lines.push((format!("{} units selected", units_num), white));
+                lines.push((format!("  Moves {:.1} at least", remaining_moves), white));
+            }
+        }
+        lines.push((String::new(), white));
+
+        // Hovered cell
+        if let Some([i, j]) = self.current_underlying_cell {
+            let terrain = self.terrain_map.borrow()[(i, j)].upgrade().map_or(String::from("None"), |terrain| terrain.name.clone());
+            lines.push((format!("Cell ({}, {})", i, j), white));
+            lines.push((format!("  {}", terrain), white));
+            lines.push((format!("  Height {:.2}", self.height_map[(i, j)]), white));
+            let owner = self.territory_map[(i, j)];
+            if !self.is_visible((i, j)) {
+                lines.push((String::from("  Out of sight"), gray));
+            }
+            else if owner < self.player_num {
+                lines.push((format!("  Owned by player {}", owner), player_color(owner)));
+            }
+            else {
+                lines.push((String::from("  Not owned"), gray));
+            }
+            if let Some(unit) = self.unit((i, j)).upgrade().filter(|_| self.is_visible((i, j))) {
+                lines.push((format!("  Unit of player {}", unit.borrow().player), player_color(unit.borrow().player)));
+            }
+            if let Some(building) = self.building((i, j)).upgrade().filter(|_| self.is_visible((i, j))) {
+                lines.push((format!("  Building of player {}", building.borrow().player), player_color(building.borrow().player)));
+            }
+            lines.push((String::new(), white));
+        }
+
+        // Territory of each player
+        let (territory, passable_cells_num) = (&self.territory_cells_num, self.passable_cells_num.max(1));
+        lines.push((String::from("Territory"), white));
+        for (player, cells_num) in territory.iter().enumerate() {
+            lines.push((
+                format!("  Player {} : {} cells ({:.1} %)", player, cells_num, 100.0 * *cells_num as f64 / passable_cells_num as f64),
+                player_color(player)
+            ));
+        }
+        lines.push((String::new(), white));
+
+        // Frame time
+        let frames_per_second = if self.frame_time > 0.0 {1.0 / self.frame_time} else {0.0};
+        lines.push((format!("Frame {:.1} ms ({:.0} FPS)", self.frame_time * 1000.0, frames_per_second), gray));
+        lines.push((format!("  Render {:.1} ms", self.render_time * 1000.0), gray));
+
+        lines
+    }
+
+    fn render_hud(&mut self, c: Context) {
+        let hud_rectangle = self.hud_rectangle();
+        if hud_rectangle[2] <= 0.0 {
+            return;
+        }
+        rectangle([0.0, 0.0, 0.0, 0.6], hud_rectangle, c.transform, self.gl.as_mut().unwrap());
+
+        // Text lines above the minimap
+        let line_height = HUD_FONT_SIZE as f64 * 1.4;
+        let (x, mut y) = (hud_rectangle[0] + HUD_PADDING, hud_rectangle[1] + HUD_PADDING);
+        let text_bottom = self.minimap_rectangle()[1] - HUD_PADDING;
+        for (line, color) in self.hud_lines() {
+            y += line_height;
+            if y > text_bottom {
+                break;
+            }
+
+            let draw_res = Text::new_color(color, HUD_FONT_SIZE).draw(
+                line.as_str(), 
+                self.glyphs.as_mut().unwrap(), 
+                &draw_state::DrawState::default(), 
+                c.transform.trans(x, y), 
+                self.gl.as_mut().unwrap()
+            );
+            if let Err(_error) = draw_res {
+                dbg!("Something went wrong when drawing HUD !");
+            }
+        }
+    }
+
+    // Minimap rectangle at the bottom of the HUD panel, keeping map proportions
+    fn minimap_rectangle(&self) -> [f64; 4] {
+        let [x, y, width, height] = self.hud_rectangle();
+        let side = (width - 2.0 * HUD_PADDING).min(height / 2.0).max(0.0);
+        let scale = side / self.map_width.max(self.map_height).max(1) as f64;
+        let (minimap_width, minimap_height) = (self.map_width as f64 * scale, self.map_height as f64 * scale);
+        [x + (width - minimap_width) / 2.0, y + height - HUD_PADDING - minimap_height, minimap_width, minimap_height]
+    }
+
+    // Map position (not rounded to a cell) under a window position on the minimap, None outside of it
+    fn minimap_position_to_map_position(&self, window_position: (f64, f64)) -> Option<(f64, f64)> {
+        let [x, y, width, height] = self.minimap_rectangle();
+        if width <= 0.0 || !is_in_rect(window_position, (x, y, x + width, y + height), false) {
+            return None;
+        }
+        Some((
+            (window_position.1 - y) / height * self.map_height as f64,
+            (window_position.0 - x) / width * self.map_width as f64
+        ))
+    }
+
+    fn render_minimap(&mut self, c: Context) {
+        let minimap_rectangle = self.minimap_rectangle();
+        let [x, y, width, height] = minimap_rectangle;
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+
+        // Terrain and territory from the cached texture, rebuilt only when outdated
+        if self.minimap_outdated || self.minimap_texture.is_none() {
+            let image = minimap_image(&self.terrain_map.borrow(), &self.territory_map, &self.players, self.visibility_map.as_ref());
+            update_texture(&mut self.minimap_texture, &image);
+            self.minimap_outdated = false;
+        }
+        if let Some(texture) = &self.minimap_texture {
+            Image::new().rect(minimap_rectangle).draw(texture, &draw_state::DrawState::default(), c.transform, self.gl.as_mut().unwrap());
+        }
+
+        // Unit dots, at least a few pixels wide
+        let scale = width / self.map_width as f64;
+        let dot_size = scale.max(3.0);
+        for player in self.players.iter() {
+            let visibility_map = self.visibility_map.as_ref();
+            for unit in player.units.iter().filter(|unit| visibility_map.is_none_or(|visibility_map| visibility_map[unit.borrow().position])) {
+                let (i, j) = unit.borrow().position;
+                let (dot_x, dot_y) = (x + (j as f64 + 0.5) * scale - dot_size / 2.0, y + (i as f64 + 0.5) * scale - dot_size / 2.0);
+                rectangle(player.principal_color, [dot_x, dot_y, dot_size, dot_size], c.transform, self.gl.as_mut().unwrap());
+            }
+        }
+
+        // View rectangle, with its copies across wrapped edges, clipped to the minimap
+        let view_rectangle = [
+            x + self.view_in_map_j * scale, y + self.view_in_map_i * scale, 
+            self.view_in_map_width * scale, self.view_in_map_height * scale
+        ];
+        let copies = |wraps: bool| if wraps {vec![-1.0, 0.0, 1.0]} else {vec![0.0]};
+        for copy_i in copies(self.wrap_mode.wraps_vertically()) {
+            for copy_j in copies(self.wrap_mode.wraps_horizontally()) {
+                let x1 = (view_rectangle[0] + copy_j * width).max(x);
+                let y1 = (view_rectangle[1] + copy_i * height).max(y);
+                let x2 = (view_rectangle[0] + view_rectangle[2] + copy_j * width).min(x + width);
+                let y2 = (view_rectangle[1] + view_rectangle[3] + copy_i * height).min(y + height);
+                if x2 > x1 && y2 > y1 {
+                    Rectangle::new_border([1.0, 1.0, 1.0, 1.0], 1.0).draw(
+                        [x1, y1, x2 - x1, y2 - y1], 
+                        &draw_state::DrawState::default(), 
+                        c.transform, 
+                        self.gl.as_mut().unwrap()
+                    );
+                }
+            }
+        }
+    }
+
     fn render(&mut self, args: &RenderArgs) {
+        // Smoothed time since the last frame
+        let render_start = Instant::now();
+        if let Some(last_frame_instant) = self.last_frame_instant {
+            let frame_time = render_start.duration_since(last_frame_instant).as_secs_f64();
+            self.frame_time += (frame_time - self.frame_time) * FRAME_TIME_SMOOTHING;
+        }
+        self.last_frame_instant = Some(render_start);
+
         // Background color
         let background_color = self.background_color;
 
@@ -1288,8 +3004,22 @@ impl<'g> Game<'g> {
         // Clear the background
         clear(background_color, self.gl.as_mut().unwrap());
 
-        // Render grid
+        // Upload tileset atlas once
+        if let (Some(tileset), None) = (&self.tileset, &self.tileset_texture) {
+            self.tileset_texture = Some(Texture::from_image(&tileset.atlas, &TextureSettings::new().filter(Filter::Nearest)));
+        }
+
+        // Only show the handover screen between two turns of hot-seat mode
+        if self.handover {
+            self.render_handover(c);
+            self.gl.as_mut().unwrap().draw_end();
+            return;
+        }
+        self.update_visibility();
+
+        // Render grid, and territory tinting cells under everything else
         self.render_grid(c, false);
+        self.render_territory(c);
 
         // Render reachable cell by active unit
         self.render_planned_path(c);
@@ -1297,11 +3027,154 @@ impl<'g> Game<'g> {
         // Render units and buildings
         self.render_buildings(c);
         self.render_units(c);
+        self.render_damage_animation(c);
 
-        // Render territory
-        self.render_territory(c);
+        // Render fog over cells out of sight in hot-seat mode, hiding their units, buildings and owner
+        self.render_fog(c);
+
+        // Render box selection being dragged
+        self.render_selection_box(c);
+
+        // Render HUD panel and its minimap
+        self.render_hud(c);
+        self.render_minimap(c);
 
         // End the drawing pipeline
         self.gl.as_mut().unwrap().draw_end();
+
+        let render_time = render_start.elapsed().as_secs_f64();
+        self.render_time += (render_time - self.render_time) * FRAME_TIME_SMOOTHING;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Headless game on a small generated map, units of the first player being selected
+    fn test_game<'g>() -> Game<'g> {
+        let mut game = Game {
+            map_width: 24,
+            map_height: 24,
+            map_preset: String::from("pangaea"),
+            map_seed: Some(5),
+            terrain_data_path: String::from(concat!(env!("CARGO_MANIFEST_DIR"), "/data/terrains.ron")),
+            unit_default_speed: 10.0,
+            player_num: 2,
+            ..Game::default()
+        };
+        game.init().unwrap();
+        let units = game.players[0].units.iter().map(Rc::downgrade).collect();
+        game.select_units(units, false);
+        game
+    }
+
+    // Order selected units to a destination and move them as far as they can this turn
+    fn move_group(game: &mut Game, destination: (usize, usize)) {
+        game.order_group(destination);
+        game.execute_group_move();
+    }
+
+    #[test]
+    fn group_ordered_onto_its_own_building_stays_around_it() {
+        let mut game = test_game();
+        let base_position = game.players[0].buildings[0].borrow().position;
+        assert!(!game.players[0].units.is_empty());
+
+        move_group(&mut game, base_position);
+        assert!(game.unit(base_position).upgrade().is_none());
+        let passable_map = game.passable_map();
+        for unit in game.players[0].units.iter() {
+            let position = unit.borrow().position;
+            assert!(passable_map[position] && game.building(position).upgrade().is_none());
+        }
+    }
+
+    #[test]
+    fn standing_order_moves_cannot_be_undone() {
+        let mut game = test_game();
+        let unit = game.players[1].units[0].clone();
+        let position = unit.borrow().position;
+        let passable_map = game.passable_map();
+        let goal = passable_map.iter_positions()
+            .filter(|(cell, passable)| **passable && game.unit(*cell).upgrade().is_none() && game.building(*cell).upgrade().is_none())
+            .map(|(cell, _)| cell)
+            .find(|cell| cell.0.abs_diff(position.0) + cell.1.abs_diff(position.1) == 3)
+            .expect("Test map should have a free cell near the unit");
+        unit.borrow_mut().goal = Some(goal);
+
+        game.turn();
+        assert_eq!(game.active_player, 1);
+        assert_ne!(unit.borrow().position, position);
+        assert!(!game.history.can_undo());
+    }
+
+    #[test]
+    fn vision_range_matches_visibility_map() {
+        for wrap_mode in [WrapMode::None, WrapMode::Full] {
+            let mut game = Game {wrap_mode, ..test_game()};
+            game.init().unwrap();
+            let visibility_map = game.player_visibility_map(0);
+            let lookouts = game.player_lookouts(0);
+            assert!(visibility_map.iter_positions().all(|(cell, visible)| *visible == game.is_seen_from(&lookouts, cell)));
+        }
+    }
+
+    #[test]
+    fn hot_seat_moves_revealing_cells_cannot_be_undone() {
+        let mut game = Game {hot_seat: true, ..test_game()};
+        game.init().unwrap();
+
+        // Units on the four neighbours of a cell see everything around it
+        let passable_map = game.passable_map();
+        let is_free = |cell: (usize, usize)| passable_map[cell] && game.unit(cell).upgrade().is_none() && game.building(cell).upgrade().is_none();
+        let center = passable_map.iter_positions()
+            .map(|(cell, _)| cell)
+            .find(|cell| is_free(*cell) && passable_map.neighbours(*cell, Neighbourhood::VonNeumann).filter(|neighbour| is_free(*neighbour)).count() == 4)
+            .expect("Test map should have a free cell with free neighbours");
+        let mut units = Vec::new();
+        for position in passable_map.neighbours(center, Neighbourhood::VonNeumann) {
+            let unit = Rc::new(RefCell::new(Unit {player: 0, position, damage: 1.0, health: 1.0, speed: 10.0, remaining_moves: 10.0, goal: None}));
+            game.unit_map.borrow_mut()[position] = Rc::downgrade(&unit);
+            game.players[0].units.push(unit.clone());
+            units.push(unit);
+        }
+
+        game.history.begin();
+        game.move_unit(&units[0], center, 1.0);
+        assert!(game.history.can_undo());
+
+        // Far away, out of sight
+        let lookouts = game.player_lookouts(0);
+        let hidden_cell = passable_map.iter_positions()
+            .map(|(cell, _)| cell)
+            .find(|cell| game.unit(*cell).upgrade().is_none() && !game.is_seen_from(&lookouts, *cell))
+            .unwrap();
+        game.move_unit(&units[0], hidden_cell, 1.0);
+        assert!(!game.history.can_undo());
+    }
+
+    #[test]
+    fn group_ordered_onto_water_stays_on_land() {
+        let mut game = test_game();
+        let passable_map = game.passable_map();
+        let base_position = game.players[0].buildings[0].borrow().position;
+        let water = passable_map.iter_positions()
+            .filter(|(position, passable)| !**passable && passable_map.neighbours(*position, Neighbourhood::VonNeumann).any(|neighbour| passable_map[neighbour]))
+            .map(|(position, _)| position)
+            .min_by_key(|position| position.0.abs_diff(base_position.0) + position.1.abs_diff(base_position.1))
+            .expect("Test map should have a shore");
+
+        // A single unit gets the cheapest cell, the destination itself if it were not water
+        let unit = Rc::downgrade(&game.players[0].units[0]);
+        game.select_units(vec![unit], false);
+        for _ in 0..5 {
+            move_group(&mut game, water);
+            for unit in game.players[0].units.iter_mut() {
+                unit.borrow_mut().remaining_moves = game.unit_default_speed;
+            }
+        }
+        assert!(game.unit(water).upgrade().is_none());
+        assert!(game.players[0].units.iter().all(|unit| passable_map[unit.borrow().position]));
     }
 }
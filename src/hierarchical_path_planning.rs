@@ -0,0 +1,757 @@
+use std::cell::RefCell;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::cmp::Ordering;
+
+use crate::distance::Distance2D;
+use crate::constraint::PositionConstraint;
+use crate::map::Map;
+use crate::path_planning::{NEIGHBORS_DIRECTION_4C, DenseAStar};
+
+// Border runs of passable cells at least this long get an entrance at each end instead of one in the middle
+const LONG_ENTRANCE_LENGTH: usize = 6;
+
+// Node of local and abstract searches, smallest cost first
+struct SearchNode {
+    position: (i32, i32),
+    cost: f64
+}
+
+impl PartialEq for SearchNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for SearchNode {}
+
+impl Ord for SearchNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for SearchNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Pair of facing cells, and edges of an abstract node with their cost
+type Entrance = ((i32, i32), (i32, i32));
+type Edges = HashMap<(i32, i32), Vec<((i32, i32), f64)>>;
+
+// Border shared by a cluster and its east or south neighbour (possibly across a wrapped edge)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Border {
+    East(usize, usize),
+    South(usize, usize)
+}
+
+/// Hierarchical abstraction of the map passability used by HPA*: the map is split into square
+/// clusters linked by entrances on their borders, and paths are first searched on the small graph
+/// of entrances before being refined to cell level inside each cluster
+pub struct HierarchicalMap {
+    pub cluster_size: usize,
+
+    // Terrain passability the abstraction is built on (also giving map dimensions and wrap mode)
+    passable_map: Map<bool>,
+    clusters_num: (usize, usize),
+
+    // Pairs of facing cells (first one in the cluster, second one in its neighbour) of each border
+    entrances: HashMap<Border, Vec<Entrance>>,
+
+    // Abstract graph: edges crossing borders and edges inside clusters, with their cost
+    inter_edges: Edges,
+    intra_edges: Edges,
+
+    // Buffers of the plain A* searches done when refinement is blocked, reused from one search to another
+    dense_astar: RefCell<DenseAStar>
+}
+
+impl HierarchicalMap {
+    /// Build the abstraction over a passability map
+    ///
+    /// # Arguments
+    ///
+    /// * `passable_map` - Cells paths can go through, whatever the units and buildings on them
+    /// * `cluster_size` - Side of clusters (in cells)
+    /// * `distance` - Cost of moving between two neighbour positions, used for edges inside clusters
+    ///
+    pub fn new(passable_map: Map<bool>, cluster_size: usize, distance: &dyn Distance2D) -> HierarchicalMap {
+        let cluster_size = cluster_size.max(2);
+        let clusters_num = (
+            passable_map.height.div_ceil(cluster_size),
+            passable_map.width.div_ceil(cluster_size)
+        );
+
+        let dense_astar = RefCell::new(DenseAStar::new(passable_map.width, passable_map.height));
+        let mut hierarchical_map = HierarchicalMap {
+            cluster_size,
+            passable_map,
+            clusters_num,
+            entrances: HashMap::new(),
+            inter_edges: HashMap::new(),
+            intra_edges: HashMap::new(),
+            dense_astar
+        };
+
+        let borders: Vec<Border> = hierarchical_map.borders().collect();
+        for border in borders {
+            hierarchical_map.build_entrances(border);
+        }
+        hierarchical_map.build_inter_edges(distance);
+        for ci in 0..clusters_num.0 {
+            for cj in 0..clusters_num.1 {
+                hierarchical_map.build_intra_edges((ci, cj), distance);
+            }
+        }
+
+        hierarchical_map
+    }
+
+    fn cluster(&self, position: (i32, i32)) -> (usize, usize) {
+        (position.0 as usize / self.cluster_size, position.1 as usize / self.cluster_size)
+    }
+
+    // Cell bounds of a cluster: first row, first column, last row and last column (included)
+    fn cluster_bounds(&self, cluster: (usize, usize)) -> (i32, i32, i32, i32) {
+        let i1 = (cluster.0 * self.cluster_size) as i32;
+        let j1 = (cluster.1 * self.cluster_size) as i32;
+        let i2 = (((cluster.0 + 1) * self.cluster_size).min(self.passable_map.height) - 1) as i32;
+        let j2 = (((cluster.1 + 1) * self.cluster_size).min(self.passable_map.width) - 1) as i32;
+        (i1, j1, i2, j2)
+    }
+
+    // Borders between clusters, across map edges along wrapped axes
+    fn borders(&self) -> impl Iterator<Item = Border> + '_ {
+        let (ni, nj) = self.clusters_num;
+        let wrap = self.passable_map.wrap;
+        (0..ni).flat_map(move |ci| (0..nj).flat_map(move |cj| {
+            let east = if cj + 1 < nj || wrap.wraps_horizontally() {Some(Border::East(ci, cj))} else {None};
+            let south = if ci + 1 < ni || wrap.wraps_vertically() {Some(Border::South(ci, cj))} else {None};
+            east.into_iter().chain(south)
+        }))
+    }
+
+    // Borders of a cluster and of its neighbours, which share entrances with it
+    fn cluster_borders(&self, cluster: (usize, usize)) -> Vec<Border> {
+        let (ni, nj) = self.clusters_num;
+        let west = (cluster.0, (cluster.1 + nj - 1) % nj);
+        let north = ((cluster.0 + ni - 1) % ni, cluster.1);
+        let candidates = [Border::East(cluster.0, cluster.1), Border::South(cluster.0, cluster.1), Border::East(west.0, west.1), Border::South(north.0, north.1)];
+        self.borders().filter(|border| candidates.contains(border)).collect()
+    }
+
+    // Facing cells of a border, first one in the cluster, second one in its east or south neighbour
+    fn border_cells(&self, border: Border) -> Vec<Entrance> {
+        let (height, width) = (self.passable_map.height as i32, self.passable_map.width as i32);
+        match border {
+            Border::East(ci, cj) => {
+                let (i1, _, i2, j2) = self.cluster_bounds((ci, cj));
+                (i1..=i2).map(|i| ((i, j2), (i, (j2 + 1) % width))).collect()
+            },
+            Border::South(ci, cj) => {
+                let (_, j1, i2, j2) = self.cluster_bounds((ci, cj));
+                (j1..=j2).map(|j| ((i2, j), ((i2 + 1) % height, j))).collect()
+            }
+        }
+    }
+
+    // Entrances of a border: one per run of facing passable cells (two for long runs)
+    fn build_entrances(&mut self, border: Border) {
+        let mut entrances = Vec::new();
+        let mut run: Vec<Entrance> = Vec::new();
+        let cells = self.border_cells(border);
+        for (k, cell_pair) in cells.iter().enumerate() {
+            if self.passable_map[cell_pair.0] && self.passable_map[cell_pair.1] {
+                run.push(*cell_pair);
+            }
+            if !run.is_empty() && (k + 1 == cells.len() || !self.passable_map[cell_pair.0] || !self.passable_map[cell_pair.1]) {
+                if run.len() >= LONG_ENTRANCE_LENGTH {
+                    entrances.push(run[0]);
+                    entrances.push(run[run.len() - 1]);
+                }
+                else {
+                    entrances.push(run[run.len() / 2]);
+                }
+                run.clear();
+            }
+        }
+        self.entrances.insert(border, entrances);
+    }
+
+    fn build_inter_edges(&mut self, distance: &dyn Distance2D) {
+        self.inter_edges.clear();
+        let entrances: Vec<Entrance> = self.entrances.values().flatten().copied().collect();
+        for entrance in entrances {
+            self.add_inter_edge(entrance, distance);
+        }
+    }
+
+    fn add_inter_edge(&mut self, (a, b): Entrance, distance: &dyn Distance2D) {
+        // Facing cells are neighbours (across map edges on wrapped borders)
+        let cost = distance.evaluate((a.0 as f64, a.1 as f64), (b.0 as f64, b.1 as f64));
+        self.inter_edges.entry(a).or_default().push((b, cost));
+        self.inter_edges.entry(b).or_default().push((a, cost));
+    }
+
+    fn remove_inter_edge(&mut self, (a, b): Entrance) {
+        for (node, other) in [(a, b), (b, a)] {
+            if let Some(edges) = self.inter_edges.get_mut(&node) {
+                edges.retain(|(neighbour, _)| *neighbour != other);
+                if edges.is_empty() {
+                    self.inter_edges.remove(&node);
+                }
+            }
+        }
+    }
+
+    // Abstract nodes lying in a cluster
+    fn cluster_nodes(&self, cluster: (usize, usize)) -> Vec<(i32, i32)> {
+        let mut nodes: Vec<(i32, i32)> = self.cluster_borders(cluster)
+            .iter()
+            .flat_map(|border| self.entrances.get(border).into_iter().flatten())
+            .flat_map(|(a, b)| vec![*a, *b])
+            .filter(|node| self.cluster(*node) == cluster)
+            .collect();
+        nodes.sort_unstable();
+        nodes.dedup();
+        nodes
+    }
+
+    fn build_intra_edges(&mut self, cluster: (usize, usize), distance: &dyn Distance2D) {
+        // Edges of the cluster nodes are all replaced, those of nodes which are no longer entrances too
+        let cluster_size = self.cluster_size;
+        self.intra_edges.retain(|node, _| (node.0 as usize / cluster_size, node.1 as usize / cluster_size) != cluster);
+
+        let nodes = self.cluster_nodes(cluster);
+
+        let passable = |position: (i32, i32)| self.passable_map[position];
+        let mut edges = Vec::new();
+        for (k, a) in nodes.iter().enumerate() {
+            let costs = self.cluster_costs(*a, &passable, distance);
+            for b in nodes.iter().skip(k + 1) {
+                if let Some(cost) = costs.get(b) {
+                    edges.push((*a, *b, *cost));
+                }
+            }
+        }
+
+        for (a, b, cost) in edges {
+            self.intra_edges.entry(a).or_default().push((b, cost));
+            self.intra_edges.entry(b).or_default().push((a, cost));
+        }
+    }
+
+    /// Update the abstraction after passability changes of some cells (terrain modified), only the entrances and
+    /// edges of the clusters containing them and of their neighbours being rebuilt
+    ///
+    /// # Arguments
+    ///
+    /// * `cells` - Positions whose passability changed, with their new passability
+    /// * `distance` - Cost of moving between two neighbour positions (same as when building)
+    ///
+    pub fn update_cells(&mut self, cells: &[((usize, usize), bool)], distance: &dyn Distance2D) {
+        let mut clusters: HashSet<(usize, usize)> = HashSet::new();
+        for (position, passable) in cells.iter() {
+            self.passable_map[*position] = *passable;
+            clusters.insert(self.cluster((position.0 as i32, position.1 as i32)));
+        }
+
+        // Entrances of changed clusters borders are rebuilt, which changes nodes of their neighbours too
+        let mut borders: HashSet<Border> = HashSet::new();
+        for cluster in clusters.iter() {
+            borders.extend(self.cluster_borders(*cluster));
+        }
+        let mut rebuilt_clusters = clusters;
+        for border in borders {
+            for entrance in self.entrances.remove(&border).unwrap_or_default() {
+                self.remove_inter_edge(entrance);
+            }
+            self.build_entrances(border);
+            for entrance in self.entrances[&border].clone() {
+                self.add_inter_edge(entrance, distance);
+            }
+            if let Some((a, b)) = self.border_cells(border).first() {
+                rebuilt_clusters.insert(self.cluster(*a));
+                rebuilt_clusters.insert(self.cluster(*b));
+            }
+        }
+
+        for cluster in rebuilt_clusters {
+            self.build_intra_edges(cluster, distance);
+        }
+    }
+
+    // Dijkstra restricted to the cluster of a position: cost of the cheapest path toward each reachable cell
+    fn cluster_costs(
+        &self,
+        start: (i32, i32),
+        passable: &dyn Fn((i32, i32)) -> bool,
+        distance: &dyn Distance2D
+    ) -> HashMap<(i32, i32), f64> {
+        let (i1, j1, i2, j2) = self.cluster_bounds(self.cluster(start));
+        let mut open_priority_queue = BinaryHeap::new();
+        let mut closed_set = HashSet::new();
+        let mut distance_from_start: HashMap<(i32, i32), f64> = HashMap::new();
+        distance_from_start.insert(start, 0.0);
+        open_priority_queue.push(SearchNode {position: start, cost: 0.0});
+
+        while let Some(current) = open_priority_queue.pop() {
+            let current_pos = current.position;
+            if !closed_set.insert(current_pos) {
+                continue;
+            }
+
+            for d in NEIGHBORS_DIRECTION_4C.iter() {
+                let neighbour = (current_pos.0 + d.0, current_pos.1 + d.1);
+                if neighbour.0 < i1 || neighbour.0 > i2 || neighbour.1 < j1 || neighbour.1 > j2 || !passable(neighbour) {
+                    continue;
+                }
+
+                let tentative_distance_from_start = current.cost + distance.evaluate(
+                    (current_pos.0 as f64, current_pos.1 as f64),
+                    (neighbour.0 as f64, neighbour.1 as f64)
+                );
+                if tentative_distance_from_start < *distance_from_start.get(&neighbour).unwrap_or(&f64::INFINITY) {
+                    distance_from_start.insert(neighbour, tentative_distance_from_start);
+                    open_priority_queue.push(SearchNode {position: neighbour, cost: tentative_distance_from_start});
+                }
+            }
+        }
+
+        distance_from_start
+    }
+
+    // A* restricted to the cluster containing both positions, with the cumulated cost at each position
+    fn cluster_astar(
+        &self,
+        start: (i32, i32),
+        goal: (i32, i32),
+        passable: &dyn Fn((i32, i32)) -> bool,
+        distance: &dyn Distance2D
+    ) -> Option<Vec<(i32, i32, f64)>> {
+        let (i1, j1, i2, j2) = self.cluster_bounds(self.cluster(start));
+        let heuristic = |position: (i32, i32)| ((position.0 - goal.0) as f64).hypot((position.1 - goal.1) as f64);
+
+        let mut open_priority_queue = BinaryHeap::new();
+        let mut closed_set = HashSet::new();
+        let mut best_previous_node: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+        let mut distance_from_start: HashMap<(i32, i32), f64> = HashMap::new();
+        distance_from_start.insert(start, 0.0);
+        open_priority_queue.push(SearchNode {position: start, cost: heuristic(start)});
+
+        while let Some(current) = open_priority_queue.pop() {
+            let current_pos = current.position;
+            if !closed_set.insert(current_pos) {
+                continue;
+            }
+
+            if current_pos == goal {
+                let mut path = vec![(goal.0, goal.1, distance_from_start[&goal])];
+                let mut position = goal;
+                while let Some(previous) = best_previous_node.get(&position) {
+                    position = *previous;
+                    path.push((position.0, position.1, distance_from_start[&position]));
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for d in NEIGHBORS_DIRECTION_4C.iter() {
+                let neighbour = (current_pos.0 + d.0, current_pos.1 + d.1);
+                if neighbour.0 < i1 || neighbour.0 > i2 || neighbour.1 < j1 || neighbour.1 > j2 || !passable(neighbour) {
+                    continue;
+                }
+
+                let tentative_distance_from_start = distance_from_start[&current_pos] + distance.evaluate(
+                    (current_pos.0 as f64, current_pos.1 as f64),
+                    (neighbour.0 as f64, neighbour.1 as f64)
+                );
+                if tentative_distance_from_start < *distance_from_start.get(&neighbour).unwrap_or(&f64::INFINITY) {
+                    distance_from_start.insert(neighbour, tentative_distance_from_start);
+                    best_previous_node.insert(neighbour, current_pos);
+                    open_priority_queue.push(SearchNode {position: neighbour, cost: tentative_distance_from_start + heuristic(neighbour)});
+                }
+            }
+        }
+
+        None
+    }
+
+    // Step between two consecutive abstract nodes at cell level (excluding the first node)
+    fn refine(
+        &self,
+        from: (i32, i32),
+        to: (i32, i32),
+        passable: &dyn Fn((i32, i32)) -> bool,
+        distance: &dyn Distance2D
+    ) -> Option<Vec<(i32, i32)>> {
+        if self.cluster(from) != self.cluster(to) || self.inter_edges.get(&from).is_some_and(|edges| edges.iter().any(|(node, _)| *node == to)) {
+            // Facing cells of an entrance
+            return if passable(to) {Some(vec![to])} else {None};
+        }
+        self.cluster_astar(from, to, passable, distance)
+            .map(|path| path.iter().skip(1).map(|(i, j, _)| (*i, *j)).collect())
+    }
+
+    /// HPA* search: same interface as astar_2d_map, abstract graph being built on terrain only,
+    /// while constraints (units, buildings...) are checked when connecting start and goal and
+    /// when refining the abstract path (falls back to dense A* if refinement fails)
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_path(
+        &self,
+        start: (i32, i32),
+        goal: (i32, i32),
+        distance: impl Distance2D,
+        heuristic: impl Distance2D,
+        way_position_constraints: Vec<Box<dyn PositionConstraint>>,
+        goal_position_constraints: Vec<Box<dyn PositionConstraint>>,
+    ) -> Option<VecDeque<(i32, i32, f64)>> {
+        let passable = |position: (i32, i32)| {
+            let cell = (position.0 as usize, position.1 as usize);
+            if position == goal {
+                goal_position_constraints.iter().all(|constraint| constraint.respect(cell))
+            }
+            else {
+                self.passable_map[position] && way_position_constraints.iter().all(|constraint| constraint.respect(cell))
+            }
+        };
+
+        // No abstract path means goal is unreachable on terrain (or start or goal is surrounded)
+        let abstract_path = self.abstract_path(start, goal, &passable, &distance, &heuristic)?;
+        let path = (|| {
+            let mut path = vec![start];
+            for nodes in abstract_path.windows(2) {
+                path.extend(self.refine(nodes[0], nodes[1], &passable, &distance)?);
+            }
+            Some(path)
+        })();
+
+        match path {
+            Some(path) => {
+                // Cumulated costs along the refined path
+                let mut total_cost = 0.0;
+                let mut total_path = VecDeque::new();
+                total_path.push_back((start.0, start.1, 0.0));
+                for step in path.windows(2) {
+                    total_cost += distance.evaluate((step[0].0 as f64, step[0].1 as f64), (step[1].0 as f64, step[1].1 as f64));
+                    total_path.push_back((step[1].0, step[1].1, total_cost));
+                }
+                Some(total_path)
+            },
+            None => {
+                // Refinement blocked by units or buildings: plain A* checking terrain and constraints on reached cells
+                self.dense_astar.borrow_mut().search_with(start, goal, self.passable_map.wrap, passable, &distance, &heuristic)
+            }
+        }
+    }
+
+    // Search on the abstract graph with start and goal temporarily linked to the nodes of their cluster
+    fn abstract_path(
+        &self,
+        start: (i32, i32),
+        goal: (i32, i32),
+        passable: &dyn Fn((i32, i32)) -> bool,
+        distance: &dyn Distance2D,
+        heuristic: &dyn Distance2D
+    ) -> Option<Vec<(i32, i32)>> {
+        let goal_f = (goal.0 as f64, goal.1 as f64);
+
+        // Temporary edges from start and toward goal
+        let mut start_edges: Vec<((i32, i32), f64)> = Vec::new();
+        let mut goal_edges: HashMap<(i32, i32), f64> = HashMap::new();
+        let start_costs = self.cluster_costs(start, passable, distance);
+        if let Some(cost) = start_costs.get(&goal) {
+            start_edges.push((goal, *cost));
+        }
+        for node in self.cluster_nodes(self.cluster(start)) {
+            if let Some(cost) = start_costs.get(&node) {
+                start_edges.push((node, *cost));
+            }
+        }
+
+        // Costs are searched from goal (distance is symmetric), its cluster nodes are passable terrain
+        let goal_costs = self.cluster_costs(goal, passable, distance);
+        for node in self.cluster_nodes(self.cluster(goal)) {
+            if let Some(cost) = goal_costs.get(&node) {
+                goal_edges.insert(node, *cost);
+            }
+        }
+
+        let mut open_priority_queue = BinaryHeap::new();
+        let mut closed_set = HashSet::new();
+        let mut best_previous_node: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+        let mut distance_from_start: HashMap<(i32, i32), f64> = HashMap::new();
+        distance_from_start.insert(start, 0.0);
+        open_priority_queue.push(SearchNode {position: start, cost: 0.0});
+
+        while let Some(current) = open_priority_queue.pop() {
+            let current_pos = current.position;
+            if !closed_set.insert(current_pos) {
+                continue;
+            }
+
+            if current_pos == goal {
+                let mut path = vec![goal];
+                let mut position = goal;
+                while let Some(previous) = best_previous_node.get(&position) {
+                    position = *previous;
+                    path.push(position);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            // Start may be an abstract node itself, keeping its edges across borders
+            let mut edges: Vec<((i32, i32), f64)> = self.inter_edges.get(&current_pos).into_iter().flatten()
+                .chain(self.intra_edges.get(&current_pos).into_iter().flatten())
+                .copied()
+                .collect();
+            if current_pos == start {
+                edges.extend(start_edges.iter().copied());
+            }
+            if let Some(cost) = goal_edges.get(&current_pos) {
+                edges.push((goal, *cost));
+            }
+
+            for (neighbour, cost) in edges {
+                let tentative_distance_from_start = distance_from_start[&current_pos] + cost;
+                if tentative_distance_from_start < *distance_from_start.get(&neighbour).unwrap_or(&f64::INFINITY) {
+                    distance_from_start.insert(neighbour, tentative_distance_from_start);
+                    best_previous_node.insert(neighbour, current_pos);
+                    let estimation = heuristic.evaluate((neighbour.0 as f64, neighbour.1 as f64), goal_f);
+                    open_priority_queue.push(SearchNode {position: neighbour, cost: tentative_distance_from_start + estimation});
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::distance::{EuclideanDistance2D, EuclideanDistanceWHeight2D, WrappedDistance2D};
+    use crate::map::WrapMode;
+    use crate::map_generator::map_generator;
+    use crate::path_planning::astar_2d_map;
+
+    // HPA* paths between cells at least a few clusters apart (the only ones the game searches hierarchically) are
+    // allowed to cost this much more than optimal ones
+    const CLUSTER_SIZE: usize = 8;
+    const MIN_DISTANCE: f64 = 4.0 * CLUSTER_SIZE as f64;
+    const MAX_COST_RATIO: f64 = 1.2;
+
+    struct PassableConstraint {
+        passable_map: Map<bool>
+    }
+
+    impl PositionConstraint for PassableConstraint {
+        fn respect(&self, pos: (usize, usize)) -> bool {
+            self.passable_map[pos]
+        }
+    }
+
+    // Generated map with low cells impassable (water), larger when wrapped since cells are closer across edges
+    fn test_maps(wrap: WrapMode) -> (Map<f64>, Map<bool>) {
+        let (width, height) = if wrap == WrapMode::Full {(80, 64)} else {(48, 40)};
+        let mut height_map = map_generator("continents").unwrap().generate(width, height, wrap, 3);
+        height_map.normalize();
+        let passable_map = height_map.map(|height| *height >= 0.3);
+        (height_map, passable_map)
+    }
+
+    // Move cost and heuristic between positions, across edges on wrapped maps
+    fn move_distance(height_map: &Map<f64>) -> WrappedDistance2D<EuclideanDistanceWHeight2D<'_>> {
+        WrappedDistance2D {
+            distance: EuclideanDistanceWHeight2D {height_map},
+            map_size: (height_map.height as f64, height_map.width as f64),
+            wrap_mode: height_map.wrap
+        }
+    }
+
+    fn heuristic(height_map: &Map<f64>) -> WrappedDistance2D<EuclideanDistance2D> {
+        WrappedDistance2D {
+            distance: EuclideanDistance2D {},
+            map_size: (height_map.height as f64, height_map.width as f64),
+            wrap_mode: height_map.wrap
+        }
+    }
+
+    fn constraints(passable_map: &Map<bool>) -> Vec<Box<dyn PositionConstraint>> {
+        vec![Box::new(PassableConstraint {passable_map: passable_map.clone()})]
+    }
+
+    type NodeEdges = ((i32, i32), Vec<((i32, i32), f64)>);
+
+    // Edges sorted by node and neighbour, their order depending on how they were built
+    fn sorted_edges(edges: &Edges) -> Vec<NodeEdges> {
+        let mut sorted_edges: Vec<NodeEdges> = edges.iter()
+            .map(|(node, node_edges)| {
+                let mut node_edges = node_edges.clone();
+                node_edges.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                (*node, node_edges)
+            })
+            .collect();
+        sorted_edges.sort_by_key(|(node, _)| *node);
+        sorted_edges
+    }
+
+    #[test]
+    fn updated_abstraction_matches_rebuilt_one() {
+        let (height_map, mut passable_map) = test_maps(WrapMode::None);
+        let distance = move_distance(&height_map);
+        let mut hierarchical_map = HierarchicalMap::new(passable_map.clone(), CLUSTER_SIZE, &distance);
+        let entrances = hierarchical_map.entrances.clone();
+
+        // Wall along a cluster border and a hole in another cluster
+        let mut cells = Vec::new();
+        for i in 10..30 {
+            cells.push(((i, 2 * CLUSTER_SIZE - 1), false));
+        }
+        for i in 2..5 {
+            for j in 33..36 {
+                cells.push(((i, j), !passable_map[(i, j)]));
+            }
+        }
+        for (position, passable) in cells.iter() {
+            passable_map[*position] = *passable;
+        }
+        hierarchical_map.update_cells(&cells, &distance);
+        assert_ne!(hierarchical_map.entrances, entrances);
+
+        let rebuilt_map = HierarchicalMap::new(passable_map.clone(), CLUSTER_SIZE, &distance);
+        assert!(hierarchical_map.passable_map.map == rebuilt_map.passable_map.map);
+        assert_eq!(hierarchical_map.entrances, rebuilt_map.entrances);
+        assert_eq!(sorted_edges(&hierarchical_map.inter_edges), sorted_edges(&rebuilt_map.inter_edges));
+        assert_eq!(sorted_edges(&hierarchical_map.intra_edges), sorted_edges(&rebuilt_map.intra_edges));
+    }
+
+    // Compare HPA* paths with A* ones between many pairs of cells of a generated map
+    fn assert_hpa_star_cost_is_close_to_astar_cost(wrap: WrapMode) {
+        let (height_map, passable_map) = test_maps(wrap);
+        let hierarchical_map = HierarchicalMap::new(passable_map.clone(), CLUSTER_SIZE, &move_distance(&height_map));
+        let passable_cells: Vec<(i32, i32)> = passable_map.iter_positions()
+            .filter(|(_, passable)| **passable)
+            .map(|((i, j), _)| (i as i32, j as i32))
+            .collect();
+        assert!(passable_cells.len() > 200);
+
+        let (mut long_paths_num, mut worst_ratio) = (0, 1.0_f64);
+        for k in 0..200 {
+            let start = passable_cells[(k * 37) % passable_cells.len()];
+            let goal = passable_cells[(k * 101 + passable_cells.len() / 2) % passable_cells.len()];
+            let astar_path = astar_2d_map(
+                start,
+                goal,
+                (passable_map.height as i32, passable_map.width as i32),
+                wrap,
+                move_distance(&height_map),
+                heuristic(&height_map),
+                constraints(&passable_map),
+                constraints(&passable_map)
+            );
+            let hpa_path = hierarchical_map.find_path(
+                start,
+                goal,
+                move_distance(&height_map),
+                heuristic(&height_map),
+                constraints(&passable_map),
+                constraints(&passable_map)
+            );
+
+            match (astar_path, hpa_path) {
+                (Some(astar_path), Some(hpa_path)) => {
+                    let (astar_cost, hpa_cost) = (astar_path.back().unwrap().2, hpa_path.back().unwrap().2);
+                    assert_eq!((hpa_path.back().unwrap().0, hpa_path.back().unwrap().1), goal);
+                    let start_to_goal = heuristic(&height_map).evaluate((start.0 as f64, start.1 as f64), (goal.0 as f64, goal.1 as f64));
+                    if start_to_goal >= MIN_DISTANCE {
+                        worst_ratio = worst_ratio.max(hpa_cost / astar_cost);
+                        long_paths_num += 1;
+                    }
+                },
+                (None, None) => {},
+                (astar_path, hpa_path) => panic!(
+                    "{:?}: A* and HPA* disagree on reachability from {:?} to {:?} ({} / {})",
+                    wrap, start, goal, astar_path.is_some(), hpa_path.is_some()
+                )
+            }
+        }
+        assert!(long_paths_num >= 10, "{:?}: only {} long paths", wrap, long_paths_num);
+        assert!(worst_ratio <= MAX_COST_RATIO, "{:?}: HPA* path costs {} times the optimal one", wrap, worst_ratio);
+    }
+
+    #[test]
+    fn hpa_star_cost_is_close_to_astar_cost() {
+        assert_hpa_star_cost_is_close_to_astar_cost(WrapMode::None);
+    }
+
+    #[test]
+    fn hpa_star_cost_is_close_to_astar_cost_on_wrapped_maps() {
+        assert_hpa_star_cost_is_close_to_astar_cost(WrapMode::Full);
+    }
+
+    #[test]
+    fn blocked_refinement_falls_back_on_plain_search() {
+        let (height_map, passable_map) = test_maps(WrapMode::Horizontal);
+        let hierarchical_map = HierarchicalMap::new(passable_map.clone(), CLUSTER_SIZE, &move_distance(&height_map));
+        let passable_cells: Vec<(i32, i32)> = passable_map.iter_positions()
+            .filter(|(_, passable)| **passable)
+            .map(|((i, j), _)| (i as i32, j as i32))
+            .collect();
+
+        let mut blocked_paths_num = 0;
+        for k in 0..50 {
+            let start = passable_cells[(k * 37) % passable_cells.len()];
+            let goal = passable_cells[(k * 101 + passable_cells.len() / 2) % passable_cells.len()];
+            let path = hierarchical_map.find_path(
+                start,
+                goal,
+                move_distance(&height_map),
+                heuristic(&height_map),
+                constraints(&passable_map),
+                constraints(&passable_map)
+            );
+
+            // Unit standing on an entrance the path goes through, which the abstract path still goes through
+            let entrance = path.into_iter().flatten()
+                .map(|(i, j, _)| (i, j))
+                .find(|position| *position != start && *position != goal && hierarchical_map.inter_edges.contains_key(position));
+            let entrance = match entrance {
+                Some(entrance) => entrance,
+                None => continue
+            };
+            let mut free_map = passable_map.clone();
+            free_map[entrance] = false;
+
+            let astar_path = astar_2d_map(
+                start,
+                goal,
+                (passable_map.height as i32, passable_map.width as i32),
+                WrapMode::Horizontal,
+                move_distance(&height_map),
+                heuristic(&height_map),
+                constraints(&free_map),
+                constraints(&free_map)
+            );
+            let hpa_path = hierarchical_map.find_path(
+                start,
+                goal,
+                move_distance(&height_map),
+                heuristic(&height_map),
+                constraints(&free_map),
+                constraints(&free_map)
+            );
+            assert_eq!(astar_path.is_some(), hpa_path.is_some());
+            if let Some(hpa_path) = hpa_path {
+                assert!(hpa_path.iter().all(|(i, j, _)| free_map[(*i as usize, *j as usize)]));
+                assert_eq!((hpa_path.back().unwrap().0, hpa_path.back().unwrap().1), goal);
+                blocked_paths_num += 1;
+            }
+        }
+        assert!(blocked_paths_num >= 5);
+    }
+}
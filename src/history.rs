@@ -0,0 +1,170 @@
+use std::cell::RefCell;
+use std::rc::Weak;
+
+use crate::player::Unit;
+
+// Unit move with what is needed to revert it
+#[derive(Debug, Clone)]
+pub struct UnitMove {
+    pub unit: Weak<RefCell<Unit>>,
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+
+    // Moves spent by the unit
+    pub cost: f64,
+
+    // Owner of the destination territory before the move
+    pub previous_owner: usize,
+
+    // Unit goal before the move (cleared once reached)
+    pub previous_goal: Option<(usize, usize)>
+}
+
+/// Undo and redo stacks of the reversible actions of the active player turn, grouped by command
+/// (all the moves of an executed path are undone at once)
+///
+/// Moves are all reversible as long as they reveal nothing hidden to the player, which is always the case
+/// without fog of war. In hot-seat mode, a move revealing new cells is recorded as irreversible.
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Vec<UnitMove>>,
+    redo_stack: Vec<Vec<UnitMove>>
+}
+
+impl History {
+    /// Forget every action (turn ended)
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Start recording a new command, undone commands can no longer be redone
+    pub fn begin(&mut self) {
+        self.redo_stack.clear();
+        self.undo_stack.push(Vec::new());
+    }
+
+    /// Record a move in the current command
+    pub fn record(&mut self, unit_move: UnitMove) {
+        match self.undo_stack.last_mut() {
+            Some(command) => command.push(unit_move),
+            None => self.undo_stack.push(vec![unit_move])
+        }
+    }
+
+    /// Record a non-undoable action (attack): nothing done before it can be undone anymore
+    pub fn record_irreversible(&mut self) {
+        self.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.undo_stack.iter().any(|command| !command.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Moves of the last command, to revert from the last to the first one
+    pub fn undo(&mut self) -> Option<Vec<UnitMove>> {
+        while let Some(command) = self.undo_stack.pop() {
+            if !command.is_empty() {
+                self.redo_stack.push(command.clone());
+                return Some(command);
+            }
+        }
+        None
+    }
+
+    /// Moves of the last undone command, to apply again from the first to the last one
+    pub fn redo(&mut self) -> Option<Vec<UnitMove>> {
+        let command = self.redo_stack.pop()?;
+        self.undo_stack.push(command.clone());
+        Some(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Move of no unit along a row, identified by its columns
+    fn unit_move(from: usize, to: usize) -> UnitMove {
+        UnitMove {unit: Weak::new(), from: (0, from), to: (0, to), cost: 1.0, previous_owner: 0, previous_goal: None}
+    }
+
+    fn columns(command: Option<Vec<UnitMove>>) -> Vec<(usize, usize)> {
+        command.unwrap().iter().map(|unit_move| (unit_move.from.1, unit_move.to.1)).collect()
+    }
+
+    #[test]
+    fn commands_are_undone_and_redone_in_order() {
+        let mut history = History::default();
+        assert!(!history.can_undo() && !history.can_redo());
+
+        history.begin();
+        history.record(unit_move(0, 1));
+        history.record(unit_move(1, 2));
+        history.begin();
+        history.record(unit_move(5, 6));
+        assert!(history.can_undo());
+
+        assert_eq!(columns(history.undo()), vec![(5, 6)]);
+        assert_eq!(columns(history.undo()), vec![(0, 1), (1, 2)]);
+        assert!(history.undo().is_none());
+        assert!(!history.can_undo() && history.can_redo());
+
+        assert_eq!(columns(history.redo()), vec![(0, 1), (1, 2)]);
+        assert_eq!(columns(history.redo()), vec![(5, 6)]);
+        assert!(history.redo().is_none());
+        assert_eq!(columns(history.undo()), vec![(5, 6)]);
+    }
+
+    #[test]
+    fn new_command_clears_redo_stack() {
+        let mut history = History::default();
+        history.begin();
+        history.record(unit_move(0, 1));
+        history.undo();
+        assert!(history.can_redo());
+
+        history.begin();
+        history.record(unit_move(3, 4));
+        assert!(!history.can_redo());
+        assert!(history.redo().is_none());
+        assert_eq!(columns(history.undo()), vec![(3, 4)]);
+    }
+
+    #[test]
+    fn irreversible_action_clears_everything() {
+        let mut history = History::default();
+        history.begin();
+        history.record(unit_move(0, 1));
+        history.begin();
+        history.record(unit_move(1, 2));
+        history.undo();
+
+        history.record_irreversible();
+        assert!(!history.can_undo() && !history.can_redo());
+        assert!(history.undo().is_none());
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn empty_commands_are_skipped() {
+        let mut history = History::default();
+        history.begin();
+        history.record(unit_move(0, 1));
+        history.begin();
+        history.begin();
+        assert!(history.can_undo());
+
+        assert_eq!(columns(history.undo()), vec![(0, 1)]);
+        assert!(!history.can_undo());
+        assert!(history.undo().is_none());
+
+        // Moves recorded without a command make one
+        history.record(unit_move(2, 3));
+        assert_eq!(columns(history.undo()), vec![(2, 3)]);
+    }
+}
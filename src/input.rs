@@ -0,0 +1,245 @@
+use std::fs;
+
+use piston::input::{Button, MouseButton};
+use piston::input::keyboard::Key;
+
+use serde::Deserialize;
+
+// Game action triggered by an input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Action {
+    // View
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    DragPan,
+    ZoomIn,
+    ZoomOut,
+    Overview,
+    LookAtBase,
+    ToggleTerrainRendering,
+
+    // Turn and orders
+    EndTurn,
+    ExecutePath,
+    Undo,
+    Redo,
+
+    // Selection (click or box drag, with Shift held to add to selection), control groups 0 to 9
+    Select,
+    SelectAll,
+    RecallControlGroup(usize),
+    AssignControlGroup(usize),
+
+    // Map
+    RegenerateMap,
+    NextMapPreset,
+    ExportSnapshot
+}
+
+// Raw input an action is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Input {
+    Key(Key),
+    Mouse(MouseButton)
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Binding {
+    pub input: Input,
+
+    // Binding used with Ctrl held (bindings without Ctrl are used with it if there is none)
+    #[serde(default)]
+    pub ctrl: bool,
+
+    pub action: Action
+}
+
+// Keyboard layout whose bindings are used by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Layout {
+    Qwerty,
+    Azerty
+}
+
+// Content of a key bindings file, bindings of the layout preset are used for inputs not bound in it
+#[derive(Debug, Clone, Deserialize)]
+struct KeyBindingsFile {
+    layout: Layout,
+    #[serde(default)]
+    bindings: Vec<Binding>
+}
+
+/// Mapping of raw piston inputs to game actions, keeping track of modifier keys
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    pub bindings: Vec<Binding>,
+    pub ctrl_pressed: bool,
+    pub shift_pressed: bool
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings::preset(Layout::Azerty)
+    }
+}
+
+impl KeyBindings {
+    /// Bindings of a keyboard layout: view panned with arrows and the keys under the left hand fingers
+    pub fn preset(layout: Layout) -> KeyBindings {
+        let key = |key: Key, action: Action| Binding {input: Input::Key(key), ctrl: false, action};
+        let ctrl_key = |key: Key, action: Action| Binding {input: Input::Key(key), ctrl: true, action};
+
+        let (up, left, select_all) = match layout {
+            Layout::Qwerty => (Key::W, Key::A, Key::Q),
+            Layout::Azerty => (Key::Z, Key::Q, Key::A)
+        };
+        let mut bindings = vec![
+            ctrl_key(Key::Z, Action::Undo),
+            ctrl_key(Key::Y, Action::Redo),
+            key(Key::Up, Action::PanUp),
+            key(up, Action::PanUp),
+            key(Key::Down, Action::PanDown),
+            key(Key::S, Action::PanDown),
+            key(Key::Left, Action::PanLeft),
+            key(left, Action::PanLeft),
+            key(Key::Right, Action::PanRight),
+            key(Key::D, Action::PanRight),
+            key(Key::PageUp, Action::ZoomIn),
+            key(Key::PageDown, Action::ZoomOut),
+            key(Key::R, Action::Overview),
+            key(Key::B, Action::LookAtBase),
+            key(Key::V, Action::ToggleTerrainRendering),
+            key(Key::T, Action::EndTurn),
+            key(Key::Space, Action::ExecutePath),
+            key(select_all, Action::SelectAll),
+            key(Key::G, Action::RegenerateMap),
+            key(Key::H, Action::NextMapPreset),
+            key(Key::P, Action::ExportSnapshot),
+            Binding {input: Input::Mouse(MouseButton::Left), ctrl: false, action: Action::Select},
+            Binding {input: Input::Mouse(MouseButton::Middle), ctrl: false, action: Action::DragPan}
+        ];
+
+        let digits = [Key::D0, Key::D1, Key::D2, Key::D3, Key::D4, Key::D5, Key::D6, Key::D7, Key::D8, Key::D9];
+        for (group, digit) in digits.iter().enumerate() {
+            bindings.push(key(*digit, Action::RecallControlGroup(group)));
+            bindings.push(ctrl_key(*digit, Action::AssignControlGroup(group)));
+        }
+
+        KeyBindings {
+            bindings,
+            ctrl_pressed: false,
+            shift_pressed: false
+        }
+    }
+
+    // Binding with Ctrl when it is held, falling back on the one without it
+    fn action(&self, input: Input) -> Option<Action> {
+        let find = |ctrl: bool| self.bindings.iter().find(|binding| binding.input == input && binding.ctrl == ctrl);
+        let binding = if self.ctrl_pressed {find(true).or_else(|| find(false))} else {find(false)};
+        binding.map(|binding| binding.action)
+    }
+
+    fn input(button: Button) -> Option<Input> {
+        match button {
+            Button::Keyboard(key) => Some(Input::Key(key)),
+            Button::Mouse(mouse_button) => Some(Input::Mouse(mouse_button)),
+            _ => None
+        }
+    }
+
+    /// Action bound to a pressed button, if any
+    pub fn press(&mut self, button: Button) -> Option<Action> {
+        match button {
+            Button::Keyboard(Key::LCtrl) | Button::Keyboard(Key::RCtrl) => self.ctrl_pressed = true,
+            Button::Keyboard(Key::LShift) | Button::Keyboard(Key::RShift) => self.shift_pressed = true,
+            _ => {}
+        }
+        KeyBindings::input(button).and_then(|input| self.action(input))
+    }
+
+    /// Action bound to a released button, if any
+    pub fn release(&mut self, button: Button) -> Option<Action> {
+        match button {
+            Button::Keyboard(Key::LCtrl) | Button::Keyboard(Key::RCtrl) => self.ctrl_pressed = false,
+            Button::Keyboard(Key::LShift) | Button::Keyboard(Key::RShift) => self.shift_pressed = false,
+            _ => {}
+        }
+        KeyBindings::input(button).and_then(|input| self.action(input))
+    }
+}
+
+/// Load key bindings from a RON file giving a layout preset and bindings overriding it
+pub fn load_key_bindings(path: &str) -> Result<KeyBindings, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|error| format!("Cannot read key bindings file '{}': {}", path, error))?;
+    let file: KeyBindingsFile = ron::de::from_str(&content)
+        .map_err(|error| format!("Cannot parse key bindings file '{}': {}", path, error))?;
+
+    for binding in file.bindings.iter() {
+        if let Action::RecallControlGroup(group) | Action::AssignControlGroup(group) = binding.action {
+            if group > 9 {
+                return Err(format!("Invalid key bindings file '{}': control group {} is not in 0..9", path, group));
+            }
+        }
+    }
+
+    // File bindings come first to be found before the preset ones
+    let mut key_bindings = KeyBindings::preset(file.layout);
+    let mut bindings = file.bindings;
+    bindings.append(&mut key_bindings.bindings);
+    key_bindings.bindings = bindings;
+
+    Ok(key_bindings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Load key bindings from a temporary file with this content
+    fn load_content(name: &str, content: &str) -> Result<KeyBindings, String> {
+        let path = std::env::temp_dir().join(format!("strategy_game_bindings_{}_{}.ron", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        let key_bindings = load_key_bindings(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        key_bindings
+    }
+
+    #[test]
+    fn file_bindings_take_priority_over_preset() {
+        let mut key_bindings = load_content("priority", "(layout: Qwerty, bindings: [(input: Key(T), action: Overview)])").unwrap();
+        assert_eq!(key_bindings.press(Button::Keyboard(Key::T)), Some(Action::Overview));
+
+        // Other inputs keep the preset bindings
+        assert_eq!(key_bindings.press(Button::Keyboard(Key::W)), Some(Action::PanUp));
+        assert_eq!(key_bindings.press(Button::Keyboard(Key::R)), Some(Action::Overview));
+        assert_eq!(key_bindings.press(Button::Mouse(MouseButton::Left)), Some(Action::Select));
+    }
+
+    #[test]
+    fn ctrl_bindings_fall_back_on_bindings_without_ctrl() {
+        let mut key_bindings = load_content("ctrl", "(layout: Azerty, bindings: [(input: Key(E), ctrl: true, action: EndTurn)])").unwrap();
+        assert_eq!(key_bindings.press(Button::Keyboard(Key::E)), None);
+
+        key_bindings.press(Button::Keyboard(Key::LCtrl));
+        assert!(key_bindings.ctrl_pressed);
+        assert_eq!(key_bindings.press(Button::Keyboard(Key::E)), Some(Action::EndTurn));
+        assert_eq!(key_bindings.press(Button::Keyboard(Key::Z)), Some(Action::Undo));
+        assert_eq!(key_bindings.press(Button::Keyboard(Key::B)), Some(Action::LookAtBase));
+
+        key_bindings.release(Button::Keyboard(Key::LCtrl));
+        assert_eq!(key_bindings.press(Button::Keyboard(Key::Z)), Some(Action::PanUp));
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        let error = load_content("group", "(layout: Qwerty, bindings: [(input: Key(F1), action: RecallControlGroup(10))])").unwrap_err();
+        assert!(error.contains("control group 10"));
+        assert!(load_content("group_9", "(layout: Qwerty, bindings: [(input: Key(F1), action: AssignControlGroup(9))])").is_ok());
+
+        assert!(load_content("syntax", "(layout: Dvorak)").unwrap_err().starts_with("Cannot parse key bindings file"));
+        assert!(load_key_bindings("missing/bindings.ron").unwrap_err().starts_with("Cannot read key bindings file"));
+    }
+}
@@ -0,0 +1,20 @@
+pub mod utils;
+pub mod distance;
+pub mod constraint;
+pub mod path_planning;
+pub mod path_cache;
+pub mod hierarchical_path_planning;
+pub mod map;
+pub mod map_generator;
+pub mod placement;
+pub mod erosion;
+pub mod biome;
+pub mod relief;
+pub mod player;
+pub mod history;
+pub mod animation;
+pub mod input;
+pub mod tileset;
+pub mod game;
+pub mod export;
+pub mod scenario;
@@ -8,41 +8,154 @@ use opengl_graphics::{GlGraphics, OpenGL, TextureSettings, Filter, GlyphCache};
 use piston::event_loop::{EventSettings, Events};
 use piston::window::WindowSettings;
 
-mod utils;
-mod distance;
-mod constraint;
-mod path_planning;
-mod map;
-mod player;
-mod game;
+use std::path::Path;
 
-use game::Game;
+use strategy_game::game::Game;
+use strategy_game::map::WrapMode;
+use strategy_game::export::export_game;
+use strategy_game::scenario::{VictoryCondition, load_scenario};
+use strategy_game::input::{KeyBindings, load_key_bindings};
+use strategy_game::tileset::load_tileset;
+use strategy_game::biome::load_terrain_data;
+use strategy_game::relief::{TerrainRendering, ReliefParameters};
 
-fn main_game() {
-    // Define OpenGL version we use
-    let opengl = OpenGL::V4_5;
+// Scale applied to the 1600x900 window
+const WINDOW_SCALE: f64 = 3.0 / 4.0;
 
-    // Create an Glutin window.
-    let factor = 3.0 / 4.0;
-    let window_width = 1600.0 * factor;
-    let window_height = 900.0 * factor;
-    let mut window: GlutinWindow = WindowSettings::new("Territory War", [window_width, window_height])
-        .graphics_api(opengl)
-        .exit_on_esc(true)
-        .resizable(false)
-        .samples(16)
-        .build()
-        .unwrap();
+// Side of a map cell in pixels in exported snapshots
+const EXPORT_CELL_SIZE: u32 = 8;
 
-    let texture_settings = TextureSettings::new().filter(Filter::Nearest);
-    let glyphs: GlyphCache = GlyphCache::new("fonts/FSEX300.ttf", (), texture_settings).expect("Failed to load font !");
+// Terrains and biomes data file
+const TERRAIN_DATA_PATH: &str = "data/terrains.ron";
 
-    // Create a new game instance and run it.
-    let map_size_level = 0;
-    let mut game = Game {
-        gl: Some(GlGraphics::new(opengl)),
-        glyphs: Some(glyphs),
+// Key bindings file used when none is given, relative to the working directory
+const DEFAULT_KEY_BINDINGS_PATH: &str = "data/bindings.ron";
 
+// Options given on command line
+struct CommandLineOptions {
+    map_preset: String,
+    height_map_path: Option<String>,
+    scenario_path: Option<String>,
+    map_width: usize,
+    map_height: usize,
+    wrap_mode: WrapMode,
+    export_directory: Option<String>,
+    key_bindings_path: Option<String>,
+    tileset_path: Option<String>,
+    terrain_rendering: TerrainRendering,
+    contour_interval: Option<f64>,
+    hot_seat: bool
+}
+
+fn parse_command_line() -> CommandLineOptions {
+    let args: Vec<String> = std::env::args().collect();
+    let mut options = CommandLineOptions {
+        map_preset: String::from("continents"),
+        height_map_path: None,
+        scenario_path: None,
+        map_width: 0,
+        map_height: 0,
+        wrap_mode: WrapMode::None,
+        export_directory: None,
+        key_bindings_path: None,
+        tileset_path: None,
+        terrain_rendering: TerrainRendering::Flat,
+        contour_interval: ReliefParameters::default().contour_interval,
+        hot_seat: false
+    };
+
+    let mut index = 1;
+    while index < args.len() {
+        let value = args.get(index + 1);
+        match (args[index].as_str(), value) {
+            // --hot-seat: players share the screen, each one only seeing around its units and buildings
+            ("--hot-seat", _) => {
+                options.hot_seat = true;
+                index += 1;
+                continue;
+            },
+            // --preset <name>
+            ("--preset", Some(preset)) => {
+                options.map_preset = preset.clone();
+            },
+            // --height-map <image path>: use a grayscale image instead of a generated height map
+            ("--height-map", Some(path)) => {
+                options.height_map_path = Some(path.clone());
+            },
+            // --scenario <path>: predefined map, players and victory conditions (other map options are ignored)
+            ("--scenario", Some(path)) => {
+                options.scenario_path = Some(path.clone());
+            },
+            // --size <width>x<height>
+            ("--size", Some(size)) => {
+                let dimensions: Vec<usize> = size.split('x').filter_map(|x| x.parse().ok()).collect();
+                if dimensions.len() == 2 && dimensions.iter().all(|dimension| *dimension >= 3) {
+                    options.map_width = dimensions[0];
+                    options.map_height = dimensions[1];
+                }
+                else {
+                    println!("Invalid map size '{}', expected <width>x<height> with dimensions of at least 3", size);
+                }
+            },
+            // --wrap <none|horizontal|full>
+            ("--wrap", Some(wrap)) => {
+                match wrap.as_str() {
+                    "none" => options.wrap_mode = WrapMode::None,
+                    "horizontal" => options.wrap_mode = WrapMode::Horizontal,
+                    "full" => options.wrap_mode = WrapMode::Full,
+                    _ => println!("Invalid wrap mode '{}', expected none, horizontal or full", wrap)
+                }
+            },
+            // --export <directory>: write PNG images of a generated game and quit without opening a window
+            ("--export", Some(directory)) => {
+                options.export_directory = Some(directory.clone());
+            },
+            // --bindings <path>: key bindings file with a keyboard layout preset (qwerty or azerty) and remapped inputs
+            ("--bindings", Some(path)) => {
+                options.key_bindings_path = Some(path.clone());
+            },
+            // --tileset <path>: sprites of units, buildings and terrains in an atlas image (shapes and colors are drawn without it)
+            ("--tileset", Some(path)) => {
+                options.tileset_path = Some(path.clone());
+            },
+            // --terrain <flat|relief>: terrain drawn with flat colors or with shaded height colors and contour lines
+            ("--terrain", Some(terrain_rendering)) => {
+                match terrain_rendering.as_str() {
+                    "flat" => options.terrain_rendering = TerrainRendering::Flat,
+                    "relief" => options.terrain_rendering = TerrainRendering::Relief,
+                    _ => println!("Invalid terrain rendering '{}', expected flat or relief", terrain_rendering)
+                }
+            },
+            // --contours <interval|none>: height difference between contour lines of relief rendering
+            ("--contours", Some(interval)) => {
+                match interval.parse::<f64>() {
+                    Ok(interval) if interval > 0.0 => options.contour_interval = Some(interval),
+                    _ if interval == "none" => options.contour_interval = None,
+                    _ => println!("Invalid contour interval '{}', expected a positive height or none", interval)
+                }
+            },
+            _ => {
+                println!("Ignored command line argument '{}'", args[index]);
+                index += 1;
+                continue;
+            }
+        }
+        index += 2;
+    }
+
+    options
+}
+
+fn exit_with_error(error: &str) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(1);
+}
+
+// Game settings shared by windowed and headless runs (without OpenGL resources)
+fn new_game<'g>(options: CommandLineOptions) -> Game<'g> {
+    let scenario = options.scenario_path.as_ref().map(|path| load_scenario(path).unwrap_or_else(|error| exit_with_error(&error)));
+
+    Game {
         background_color: [0.4, 0.0, 0.0, 1.0],
 
         grid_line_color: [0.0, 0.0, 0.0, 1.0],
@@ -51,28 +164,100 @@ fn main_game() {
         view_in_window_x: 32.0,
         view_in_window_y: 32.0,
 
-        view_in_window_width: 800.0 * factor,
-        view_in_window_height: 800.0 * factor,
+        view_in_window_width: 800.0 * WINDOW_SCALE,
+        view_in_window_height: 800.0 * WINDOW_SCALE,
+
+        window_width: 1600.0 * WINDOW_SCALE,
+        window_height: 900.0 * WINDOW_SCALE,
 
-        map_size_level: map_size_level,
+        map_size_level: 0,
 
-        view_in_map_i: 0.0,
-        view_in_map_j: 0.0,
+        map_width: options.map_width,
+        map_height: options.map_height,
+        wrap_mode: options.wrap_mode,
 
-        view_in_map_width: (2 as u32).pow(4+map_size_level) as f64+1.0,
-        view_in_map_height: (2 as u32).pow(4+map_size_level) as f64+1.0,
+        map_preset: options.map_preset,
+        height_map_path: options.height_map_path,
+        terrain_data_path: String::from(TERRAIN_DATA_PATH),
 
         unit_default_speed: 10.0,
         player_num: 2,
 
-        color_ramp_value: vec![0.0, 1.0],
-        color_ramp_color: vec![[0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0, 1.0]],
+        scenario,
+        victory_conditions: vec![VictoryCondition::Elimination],
+
+        // Hypsometric tints following terrain height intervals
+        color_ramp_value: vec![0.0, 0.2, 0.275, 0.3, 0.5, 0.7, 0.9, 1.0],
+        color_ramp_color: vec![
+            [0.007, 0.176, 0.357, 1.0], 
+            [0.051, 0.286, 0.404, 1.0], 
+            [0.98, 0.84, 0.45, 1.0], 
+            [0.204, 0.412, 0.180, 1.0], 
+            [0.592, 0.620, 0.325, 1.0], 
+            [0.557, 0.541, 0.341, 1.0], 
+            [0.75, 0.75, 0.75, 1.0], 
+            [1.0, 1.0, 1.0, 1.0]
+        ],
+        terrain_rendering: options.terrain_rendering,
+        hot_seat: options.hot_seat,
+        relief: ReliefParameters {
+            contour_interval: options.contour_interval,
+            ..ReliefParameters::default()
+        },
 
         ..Game::default()
+    }
+}
+
+fn main_game(options: CommandLineOptions) {
+    // Define OpenGL version we use
+    let opengl = OpenGL::V4_5;
+
+    // Create an Glutin window.
+    let window_width = 1600.0 * WINDOW_SCALE;
+    let window_height = 900.0 * WINDOW_SCALE;
+    let mut window: GlutinWindow = WindowSettings::new("Territory War", [window_width, window_height])
+        .graphics_api(opengl)
+        .exit_on_esc(true)
+        .resizable(false)
+        .samples(16)
+        .build()
+        .unwrap();
+
+    let texture_settings = TextureSettings::new().filter(Filter::Nearest);
+    let glyphs: GlyphCache = GlyphCache::new("fonts/FSEX300.ttf", (), texture_settings).expect("Failed to load font !");
+
+    // Create a new game instance and run it.
+    // A given key bindings file must be valid, the default one falls back on the default layout preset
+    let key_bindings = match &options.key_bindings_path {
+        Some(path) => load_key_bindings(path).unwrap_or_else(|error| exit_with_error(&error)),
+        None => load_key_bindings(DEFAULT_KEY_BINDINGS_PATH).unwrap_or_else(|error| {
+            if Path::new(DEFAULT_KEY_BINDINGS_PATH).exists() {
+                println!("{}, using default key bindings", error);
+            }
+            KeyBindings::default()
+        })
     };
 
+    // Missing tileset assets fall back on drawing shapes and colors
+    let tileset = options.tileset_path.as_ref().and_then(|path| {
+        let tileset = load_terrain_data(TERRAIN_DATA_PATH).and_then(|terrain_data| load_tileset(path, &terrain_data.terrains));
+        match tileset {
+            Ok(tileset) => Some(tileset),
+            Err(error) => {
+                println!("{}, drawing shapes instead of sprites", error);
+                None
+            }
+        }
+    });
+    let mut game = new_game(options);
+    game.key_bindings = key_bindings;
+    game.tileset = tileset;
+    game.gl = Some(GlGraphics::new(opengl));
+    game.glyphs = Some(glyphs);
+
     // Initialize game
-    game.init();
+    game.init().unwrap_or_else(|error| exit_with_error(&error));
 
     // Events processing loop
     let mut events: Events = Events::new(EventSettings::new());
@@ -81,7 +266,28 @@ fn main_game() {
     }
 }
 
+// Generate a game and export its images without any window (used for CI artifacts)
+fn export_new_game(options: CommandLineOptions, directory: &str) {
+    let mut game = new_game(options);
+    game.init().unwrap_or_else(|error| exit_with_error(&error));
+
+    match export_game(&game, Path::new(directory), EXPORT_CELL_SIZE) {
+        Ok(paths) => {
+            for path in paths {
+                println!("Exported {}", path);
+            }
+        },
+        Err(error) => exit_with_error(&error)
+    }
+}
+
 fn main() {
-    // Launch game
-    main_game();
+    // Read options from command line
+    let options = parse_command_line();
+
+    // Launch game, or only export it
+    match options.export_directory.clone() {
+        Some(directory) => export_new_game(options, &directory),
+        None => main_game(options)
+    }
 }
@@ -1,17 +1,75 @@
 use rand::prelude::*;
-use noise::{NoiseFn, Seedable, SuperSimplex};
+use rand::rngs::StdRng;
+use noise::{NoiseFn, Seedable, SuperSimplex, OpenSimplex};
 use std::ops::{Index, IndexMut};
 use std::collections::VecDeque;
 
+use serde::Deserialize;
+
+use crate::path_planning::{NEIGHBORS_DIRECTION_4C, NEIGHBORS_DIRECTION_8C};
+
 // pub trait MapType {}
 // impl MapType for f64 {}
 // impl MapType for i32 {}
 
-#[derive(Default)]
+// Map edges connected to each other: none, left and right (cylinder) or all of them (torus)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum WrapMode {
+    #[default]
+    None,
+    Horizontal,
+    Full
+}
+
+impl WrapMode {
+    pub fn wraps_horizontally(&self) -> bool {
+        *self != WrapMode::None
+    }
+
+    pub fn wraps_vertically(&self) -> bool {
+        *self == WrapMode::Full
+    }
+
+    /// Position brought back into a map of passed size (height, width) along wrapped axes, None if it stays outside
+    pub fn wrap_position(&self, pos: (i32, i32), map_size: (i32, i32)) -> Option<(i32, i32)> {
+        let i = if self.wraps_vertically() {pos.0.rem_euclid(map_size.0)} else {pos.0};
+        let j = if self.wraps_horizontally() {pos.1.rem_euclid(map_size.1)} else {pos.1};
+
+        if i >= 0 && i < map_size.0 && j >= 0 && j < map_size.1 {
+            Some((i, j))
+        }
+        else {
+            None
+        }
+    }
+
+    /// Shortest displacement from a position to another in a map of passed size (height, width)
+    pub fn delta(&self, pos1: (f64, f64), pos2: (f64, f64), map_size: (f64, f64)) -> (f64, f64) {
+        let shortest = |d: f64, size: f64, wrapped: bool| {
+            if wrapped {
+                let d = d.rem_euclid(size);
+                if d > size / 2.0 {d - size} else {d}
+            }
+            else {
+                d
+            }
+        };
+
+        (
+            shortest(pos2.0 - pos1.0, map_size.0, self.wraps_vertically()),
+            shortest(pos2.1 - pos1.1, map_size.1, self.wraps_horizontally())
+        )
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct Map<T> {
     pub width: usize,
     pub height: usize,
     pub map: Vec<T>,
+
+    // Wrap mode used when indexing with signed positions
+    pub wrap: WrapMode,
 }
 
 // impl<T: PartialOrd + Sub<Output = T> + Div<Output = T> + Ord> Map<T> {
@@ -41,64 +99,257 @@ impl<T: Clone> Map<T> {
         map.resize(width * height, value);
 
         Map::<T> {
-            width: width,
-            height: height,
-            map
+            width,
+            height,
+            map,
+            wrap: WrapMode::None
         }
     }
 }
 
+// Neighbourhood definition: 4 (Von Neumann) or 8 (Moore) surrounding cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighbourhood {
+    VonNeumann,
+    Moore
+}
+
+impl Neighbourhood {
+    pub fn directions(&self) -> &'static [(i32, i32)] {
+        match self {
+            Neighbourhood::VonNeumann => &NEIGHBORS_DIRECTION_4C,
+            Neighbourhood::Moore => &NEIGHBORS_DIRECTION_8C
+        }
+    }
+}
+
+impl<T> Map<T> {
+    fn ravel(&self, pos: (usize, usize)) -> usize {
+        let (i, j) = pos;
+        assert!(i < self.height && j < self.width, "Position {:?} is out of map ({}x{})", pos, self.width, self.height);
+        i * self.width + j
+    }
+
+    /// Signed position brought back into the map along wrapped axes
+    pub fn wrap_position(&self, pos: (i32, i32)) -> Option<(i32, i32)> {
+        self.wrap.wrap_position(pos, (self.height as i32, self.width as i32))
+    }
+
+    /// Value at a signed position (brought back into the map along wrapped axes), None if out of map
+    pub fn get(&self, pos: (i32, i32)) -> Option<&T> {
+        let (i, j) = self.wrap_position(pos)?;
+        self.map.get(i as usize * self.width + j as usize)
+    }
+
+    pub fn get_mut(&mut self, pos: (i32, i32)) -> Option<&mut T> {
+        let (i, j) = self.wrap_position(pos)?;
+        self.map.get_mut(i as usize * self.width + j as usize)
+    }
+
+    /// Iterator over all cells in row-major order with their position
+    pub fn iter_positions(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        let width = self.width;
+        self.map.iter().enumerate().map(move |(k, value)| ((k / width, k % width), value))
+    }
+
+    /// Iterator over the neighbours of a position (across edges on wrapped maps)
+    pub fn neighbours(&self, pos: (usize, usize), neighbourhood: Neighbourhood) -> impl Iterator<Item = (usize, usize)> + '_ {
+        neighbourhood
+            .directions()
+            .iter()
+            .filter_map(move |d| self.wrap_position((pos.0 as i32 + d.0, pos.1 as i32 + d.1)))
+            .map(|(i, j)| (i as usize, j as usize))
+    }
+
+    /// New map of the same dimensions holding a function of each value
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Map<U> {
+        Map::<U> {
+            width: self.width,
+            height: self.height,
+            map: self.map.iter().map(f).collect(),
+            wrap: self.wrap
+        }
+    }
+
+    /// New map of the same dimensions holding a function of the values of both maps at each position
+    pub fn zip_with<U, V, F: FnMut(&T, &U) -> V>(&self, other: &Map<U>, mut f: F) -> Map<V> {
+        assert!(self.width == other.width && self.height == other.height, "Zipped maps must have the same dimensions");
+        Map::<V> {
+            width: self.width,
+            height: self.height,
+            map: self.map.iter().zip(other.map.iter()).map(|(x, y)| f(x, y)).collect(),
+            wrap: self.wrap
+        }
+    }
+
+    pub fn row(&self, i: usize) -> &[T] {
+        let start = self.ravel((i, 0));
+        &self.map[start..(start + self.width)]
+    }
+
+    pub fn row_mut(&mut self, i: usize) -> &mut [T] {
+        let start = self.ravel((i, 0));
+        &mut self.map[start..(start + self.width)]
+    }
+
+    /// Iterator over the values of a column (not contiguous in memory)
+    pub fn column(&self, j: usize) -> impl Iterator<Item = &T> {
+        assert!(j < self.width, "Column {} is out of map ({}x{})", j, self.width, self.height);
+        self.map.iter().skip(j).step_by(self.width)
+    }
+
+    /// Read-only view over the rectangular region starting at (i, j) with passed dimensions
+    pub fn view(&self, i: usize, j: usize, width: usize, height: usize) -> MapView<'_, T> {
+        assert!(i + height <= self.height && j + width <= self.width, "View is out of map ({}x{})", self.width, self.height);
+        MapView {
+            map: self,
+            i,
+            j,
+            width,
+            height
+        }
+    }
+
+    pub fn swap(&mut self, pos1: (usize, usize), pos2: (usize, usize)) {
+        let ravel_pos1 = self.ravel(pos1);
+        let ravel_pos2 = self.ravel(pos2);
+
+        self.map.swap(ravel_pos1, ravel_pos2);
+    }
+}
+
+impl<T: Clone> Map<T> {
+    pub fn fill(&mut self, value: T) {
+        for x in self.map.iter_mut() {
+            *x = value.clone();
+        }
+    }
+
+    /// Copy of the rectangular region starting at (i, j) with passed dimensions
+    pub fn crop(&self, i: usize, j: usize, width: usize, height: usize) -> Map<T> {
+        self.view(i, j, width, height).to_map()
+    }
+}
+
 impl<T> Index<(usize, usize)> for Map<T> {
     type Output = T;
     fn index(&self, pos: (usize, usize)) -> &Self::Output {
-        let (i, j) = pos;
-        &self.map[i * self.width + j]
+        &self.map[self.ravel(pos)]
     }
 }
 
 impl<T> IndexMut<(usize, usize)> for Map<T> {
     fn index_mut(&mut self, pos: (usize, usize)) -> &mut Self::Output {
-        let (i, j) = pos;
-        &mut self.map[i * self.width + j]
+        let ravel_pos = self.ravel(pos);
+        &mut self.map[ravel_pos]
     }
 }
 
 impl<T> Index<(i32, i32)> for Map<T> {
     type Output = T;
     fn index(&self, pos: (i32, i32)) -> &Self::Output {
-        let (i, j) = pos;
-        &self.map[i as usize * self.width + j as usize]
+        match self.get(pos) {
+            Some(value) => value,
+            None => panic!("Position {:?} is out of map ({}x{})", pos, self.width, self.height)
+        }
     }
 }
 
 impl<T> IndexMut<(i32, i32)> for Map<T> {
     fn index_mut(&mut self, pos: (i32, i32)) -> &mut Self::Output {
-        let (i, j) = pos;
-        &mut self.map[i as usize * self.width + j as usize]
+        let (width, height) = (self.width, self.height);
+        match self.get_mut(pos) {
+            Some(value) => value,
+            None => panic!("Position {:?} is out of map ({}x{})", pos, width, height)
+        }
     }
 }
 
-impl<T> Map<T> {
-    pub fn swap(&mut self, pos1: (usize, usize), pos2: (usize, usize)) {
-        let ravel_pos1 = pos1.0 * self.width + pos1.1;
-        let ravel_pos2 = pos2.0 * self.width + pos2.1;
+// Read-only rectangular region of a map, positions are relative to the region origin
+pub struct MapView<'m, T> {
+    map: &'m Map<T>,
+    i: usize,
+    j: usize,
+    pub width: usize,
+    pub height: usize
+}
 
-        self.map.swap(ravel_pos1, ravel_pos2);
+impl<'m, T> MapView<'m, T> {
+    pub fn get(&self, pos: (usize, usize)) -> Option<&'m T> {
+        if pos.0 < self.height && pos.1 < self.width {
+            Some(&self.map[(self.i + pos.0, self.j + pos.1)])
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn row(&self, i: usize) -> &'m [T] {
+        &self.map.row(self.i + i)[self.j..(self.j + self.width)]
+    }
+
+    /// Iterator over all cells of the region with their position relative to the region origin
+    pub fn iter_positions(&self) -> impl Iterator<Item = ((usize, usize), &'m T)> + '_ {
+        (0..self.height).flat_map(move |i| self.row(i).iter().enumerate().map(move |(j, value)| ((i, j), value)))
+    }
+}
+
+impl<'m, T: Clone> MapView<'m, T> {
+    pub fn to_map(&self) -> Map<T> {
+        let mut map = Vec::with_capacity(self.width * self.height);
+        for i in 0..self.height {
+            map.extend_from_slice(self.row(i));
+        }
+
+        Map::<T> {
+            width: self.width,
+            height: self.height,
+            map,
+            wrap: WrapMode::None
+        }
     }
 }
 
-pub fn diamond_square(map_size: usize) -> Map<f64> {
+impl<'m, T> Index<(usize, usize)> for MapView<'m, T> {
+    type Output = T;
+    fn index(&self, pos: (usize, usize)) -> &Self::Output {
+        match self.get(pos) {
+            Some(value) => value,
+            None => panic!("Position {:?} is out of map view ({}x{})", pos, self.width, self.height)
+        }
+    }
+}
+
+pub fn diamond_square(width: usize, height: usize, wrap: WrapMode, seed: u64) -> Map<f64> {
+    // Diamond-square works on square maps of size 2^n+1, generate on the smallest one containing the map
+    let mut map_size = 3;
+    while map_size < width.max(height) {
+        map_size = (map_size - 1) * 2 + 1;
+    }
+
+    // Along wrapped axes, last row/column is a copy of the first one and neighbours are found across edges
+    let period = map_size as i32 - 1;
+    let (wrap_i, wrap_j) = (wrap.wraps_vertically(), wrap.wraps_horizontally());
+
     // Initialisation de la carte
     let mut height_map: Map<f64> = Map::<f64>::new(map_size, map_size, 0.0);
 
     // Initialisation du générateur de nombre aléatoire
-    let mut rng = rand::thread_rng();
+    let mut rng = StdRng::seed_from_u64(seed);
 
     // Initialisation des coins
     height_map[(0, 0)] = rng.gen_range((-(map_size as f64))..=(map_size as f64));
     height_map[(0, map_size-1)] = rng.gen_range((-(map_size as f64))..=(map_size as f64));
     height_map[(map_size-1, 0)] = rng.gen_range((-(map_size as f64))..=(map_size as f64));
     height_map[(map_size-1, map_size-1)] = rng.gen_range((-(map_size as f64))..=(map_size as f64));
+    if wrap_j {
+        height_map[(0, map_size-1)] = height_map[(0, 0)];
+        height_map[(map_size-1, map_size-1)] = height_map[(map_size-1, 0)];
+    }
+    if wrap_i {
+        height_map[(map_size-1, 0)] = height_map[(0, 0)];
+        height_map[(map_size-1, map_size-1)] = height_map[(0, map_size-1)];
+    }
 
     let mut diamond_square_queue: VecDeque<(i32, i32, i32)> = VecDeque::new();
     diamond_square_queue.push_back((0, 0, map_size as i32 - 1));
@@ -119,7 +370,9 @@ pub fn diamond_square(map_size: usize) -> Map<f64> {
             let mut value_num: i32 = 0;
             let mut value_sum: f64 = 0.0;
             for (odpi, odpj) in offsets_to_find_diamond_points.iter() {
-                let (dpi, dpj) = (pi + odpi, pj + odpj);
+                let (mut dpi, mut dpj) = (pi + odpi, pj + odpj);
+                if wrap_i {dpi = dpi.rem_euclid(period);}
+                if wrap_j {dpj = dpj.rem_euclid(period);}
                 if dpi >= 0 && dpi < map_size as i32 && dpj >= 0 && dpj < map_size as i32 {
                     value_num += 1;
                     value_sum += height_map[(dpi as usize, dpj as usize)];
@@ -127,7 +380,16 @@ pub fn diamond_square(map_size: usize) -> Map<f64> {
             }
 
             let rand_add = rng.gen_range((-(s as f64))..=(s as f64));
-            height_map[(pi as usize, pj as usize)] = ((value_sum as f64) / (value_num as f64)) + rand_add;
+            let value = (value_sum / (value_num as f64)) + rand_add;
+            height_map[(pi as usize, pj as usize)] = value;
+
+            // Keep copies of wrapped edges identical
+            if wrap_j && (pj == 0 || pj == period) {
+                height_map[(pi as usize, (period - pj) as usize)] = value;
+            }
+            if wrap_i && (pi == 0 || pi == period) {
+                height_map[((period - pi) as usize, pj as usize)] = value;
+            }
         }
 
         // Population de la file d'attente
@@ -139,21 +401,50 @@ pub fn diamond_square(map_size: usize) -> Map<f64> {
         }
     }
 
+    // Crop to requested dimensions along bounded axes and stretch the period along wrapped ones
+    let mut cropped_map = Map::<f64>::new(width, height, 0.0);
+    let scale_i = if wrap_i {period as f64 / height as f64} else {1.0};
+    let scale_j = if wrap_j {period as f64 / width as f64} else {1.0};
+    for i in 0..height {
+        for j in 0..width {
+            // Bilinear interpolation of the generated map
+            let (si, sj) = (i as f64 * scale_i, j as f64 * scale_j);
+            let (i0, j0) = (si.floor() as usize, sj.floor() as usize);
+            let (i1, j1) = ((i0 + 1).min(period as usize), (j0 + 1).min(period as usize));
+            let (u, v) = (si - i0 as f64, sj - j0 as f64);
+            cropped_map[(i, j)] =
+                height_map[(i0, j0)] * (1.0 - u) * (1.0 - v) + height_map[(i0, j1)] * (1.0 - u) * v +
+                height_map[(i1, j0)] * u * (1.0 - v) + height_map[(i1, j1)] * u * v;
+        }
+    }
+
     // Normalize height map
+    let mut height_map = cropped_map;
+    height_map.wrap = wrap;
     height_map.normalize();
 
     height_map
 }
 
-pub fn noise_map(map_size: usize, octaves_num: usize, lacunarity: f64, persistance: f64, power: f64, island: bool) -> Map<f64> {
+#[allow(clippy::too_many_arguments)]
+pub fn noise_map(width: usize, height: usize, octaves_num: usize, lacunarity: f64, persistance: f64, power: f64, island: bool, wrap: WrapMode, seed: u64) -> Map<f64> {
     // Initialize noise map
-    let mut noise_map: Map<f64> = Map::<f64>::new(map_size, map_size, 0.0);
+    let mut noise_map: Map<f64> = Map::<f64>::new(width, height, 0.0);
+
+    // Sample noise with the same scale on both axes so that features are not stretched
+    let scale = width.max(height) as f64;
 
-    // Initialize random number generator
-    let mut rng = rand::thread_rng();
+    // Wrapped axes are sampled on circles whose perimeter keeps the same scale (seamless noise)
+    let tau = 2.0 * std::f64::consts::PI;
+    let radius_i = height as f64 / (tau * scale);
+    let radius_j = width as f64 / (tau * scale);
 
-    // Initialize a SuperSimplex noise generator
+    // Initialize random number generator (same seed, same map)
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Initialize a SuperSimplex noise generator (and an OpenSimplex one for 4D sampling on torus)
     let mut noise_gen = SuperSimplex::new();
+    let mut torus_noise_gen = OpenSimplex::new();
 
     for octave in 0..(octaves_num as i32) {
         let f = lacunarity.powi(octave);
@@ -161,13 +452,24 @@ pub fn noise_map(map_size: usize, octaves_num: usize, lacunarity: f64, persistan
 
         // Set a different seed for each ferquency
         noise_gen = noise_gen.set_seed(rng.gen());
+        torus_noise_gen = torus_noise_gen.set_seed(rng.gen());
 
         // Apply noise to map
-        for i in 0..(map_size as i32) {
-            for j in 0..(map_size as i32) {
-                let ni = (i as f64 / map_size as f64) - 0.5;
-                let nj = (j as f64 / map_size as f64) - 0.5;
-                noise_map[(i, j)] += w * noise_gen.get([f * ni, f * nj]);
+        for i in 0..(height as i32) {
+            for j in 0..(width as i32) {
+                let ni = (i as f64 / scale) - 0.5;
+                let nj = (j as f64 / scale) - 0.5;
+                let ai = tau * i as f64 / height as f64;
+                let aj = tau * j as f64 / width as f64;
+                let value = match wrap {
+                    WrapMode::None => noise_gen.get([f * ni, f * nj]),
+                    WrapMode::Horizontal => noise_gen.get([f * ni, f * radius_j * aj.cos(), f * radius_j * aj.sin()]),
+                    WrapMode::Full => torus_noise_gen.get([
+                        f * radius_i * ai.cos(), f * radius_i * ai.sin(), 
+                        f * radius_j * aj.cos(), f * radius_j * aj.sin()
+                    ])
+                };
+                noise_map[(i, j)] += w * value;
             }
         }
     }
@@ -175,13 +477,15 @@ pub fn noise_map(map_size: usize, octaves_num: usize, lacunarity: f64, persistan
     // Normalize noise map
     noise_map.normalize();
 
-    if island {
-        // Apply island transform to map
-        let half = (map_size - 1) as f64 / 2.0;
-        let dmax = (half.powi(2) * 2.0).sqrt();
-        for i in 0..(map_size as i32) {
-            for j in 0..(map_size as i32) {
-                let d = ((i as f64 - half).powi(2) + (j as f64 - half).powi(2)).sqrt();
+    if island && wrap != WrapMode::Full {
+        // Apply island transform to map (only along bounded axes)
+        let half_height = ((height - 1) as f64 / 2.0).max(f64::EPSILON);
+        let half_width = ((width - 1) as f64 / 2.0).max(f64::EPSILON);
+        let j_weight: f64 = if wrap.wraps_horizontally() {0.0} else {1.0};
+        let dmax = (1.0 + j_weight).sqrt();
+        for i in 0..(height as i32) {
+            for j in 0..(width as i32) {
+                let d = (((i as f64 - half_height) / half_height).powi(2) + j_weight * ((j as f64 - half_width) / half_width).powi(2)).sqrt();
                 noise_map[(i, j)] *= -d / dmax + 1.0;
                 // noise_map[(i, j)] *= (1.0 / d) / (1.0 / dmax);
             }
@@ -193,6 +497,114 @@ pub fn noise_map(map_size: usize, octaves_num: usize, lacunarity: f64, persistan
 
     // Normalize noise map
     noise_map.normalize();
+    noise_map.wrap = wrap;
 
     noise_map
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3 rows x 4 columns map holding i * 10 + j
+    fn test_map() -> Map<i32> {
+        let mut map = Map::new(4, 3, 0);
+        for i in 0..3 {
+            for j in 0..4 {
+                map[(i, j)] = i * 10 + j;
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn get_is_bounds_checked() {
+        let map = test_map();
+        assert_eq!(map.get((2, 3)), Some(&23));
+        assert_eq!(map.get((0, 4)), None);
+        assert_eq!(map.get((3, 0)), None);
+        assert_eq!(map.get((-1, 0)), None);
+    }
+
+    #[test]
+    fn get_wraps_along_wrapped_axes() {
+        let mut map = test_map();
+        map.wrap = WrapMode::Horizontal;
+        assert_eq!(map.get((1, -1)), Some(&13));
+        assert_eq!(map.get((1, 4)), Some(&10));
+        assert_eq!(map.get((-1, 0)), None);
+
+        map.wrap = WrapMode::Full;
+        assert_eq!(map.get((-1, 0)), Some(&20));
+        *map.get_mut((3, 5)).unwrap() = -1;
+        assert_eq!(map[(0usize, 1usize)], -1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_panics_on_out_of_range_column() {
+        let map = test_map();
+        let _ = map[(0usize, 4usize)];
+    }
+
+    #[test]
+    fn iter_positions_is_row_major() {
+        let map = test_map();
+        let positions: Vec<((usize, usize), i32)> = map.iter_positions().map(|(pos, value)| (pos, *value)).take(5).collect();
+        assert_eq!(positions, vec![((0, 0), 0), ((0, 1), 1), ((0, 2), 2), ((0, 3), 3), ((1, 0), 10)]);
+        assert!(map.iter_positions().all(|((i, j), value)| *value == (i * 10 + j) as i32));
+    }
+
+    #[test]
+    fn neighbours_respect_edges_and_wrap() {
+        let mut map = test_map();
+        let mut neighbours: Vec<(usize, usize)> = map.neighbours((0, 0), Neighbourhood::VonNeumann).collect();
+        neighbours.sort();
+        assert_eq!(neighbours, vec![(0, 1), (1, 0)]);
+        assert_eq!(map.neighbours((1, 1), Neighbourhood::Moore).count(), 8);
+        assert_eq!(map.neighbours((0, 0), Neighbourhood::Moore).count(), 3);
+
+        map.wrap = WrapMode::Horizontal;
+        let mut neighbours: Vec<(usize, usize)> = map.neighbours((0, 0), Neighbourhood::VonNeumann).collect();
+        neighbours.sort();
+        assert_eq!(neighbours, vec![(0, 1), (0, 3), (1, 0)]);
+    }
+
+    #[test]
+    fn map_and_zip_with_keep_dimensions() {
+        let map = test_map();
+        let doubled = map.map(|x| x * 2);
+        assert_eq!((doubled.width, doubled.height), (4, 3));
+        assert_eq!(doubled[(2usize, 1usize)], 42);
+
+        let summed = map.zip_with(&doubled, |x, y| (x + y) as f64);
+        assert_eq!(summed[(1usize, 2usize)], 36.0);
+    }
+
+    #[test]
+    fn fill_rows_and_columns() {
+        let mut map = test_map();
+        assert_eq!(map.row(1), &[10, 11, 12, 13]);
+        assert_eq!(map.column(2).copied().collect::<Vec<i32>>(), vec![2, 12, 22]);
+
+        map.row_mut(0).copy_from_slice(&[5, 5, 5, 5]);
+        assert_eq!(map.column(0).copied().collect::<Vec<i32>>(), vec![5, 10, 20]);
+
+        map.fill(7);
+        assert!(map.map.iter().all(|x| *x == 7));
+    }
+
+    #[test]
+    fn view_and_crop_are_relative_to_region() {
+        let map = test_map();
+        let view = map.view(1, 1, 2, 2);
+        assert_eq!(view[(0, 0)], 11);
+        assert_eq!(view.get((1, 1)), Some(&22));
+        assert_eq!(view.get((2, 0)), None);
+        assert_eq!(view.row(1), &[21, 22]);
+        assert_eq!(view.iter_positions().count(), 4);
+
+        let cropped = map.crop(1, 2, 2, 2);
+        assert_eq!(cropped.map, vec![12, 13, 22, 23]);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::map::{Map, WrapMode, diamond_square, noise_map};
+
+/// Trait implemented by every height map generator preset
+pub trait MapGenerator {
+    fn name(&self) -> &str;
+    fn generate(&self, width: usize, height: usize, wrap: WrapMode, seed: u64) -> Map<f64>;
+}
+
+// Fractal noise parameters shared by the noise-based presets, each one having its own defaults
+#[derive(Debug, Clone, Copy)]
+pub struct NoiseParameters {
+    pub octaves_num: usize,
+    pub lacunarity: f64,
+    pub persistance: f64,
+
+    // Power applied to heights (below 1 raises the relief)
+    pub power: f64
+}
+
+impl NoiseParameters {
+    fn noise_map(&self, width: usize, height: usize, island: bool, wrap: WrapMode, seed: u64) -> Map<f64> {
+        noise_map(width, height, self.octaves_num, self.lacunarity, self.persistance, self.power, island, wrap, seed)
+    }
+}
+
+// Continents: plain fractal noise, several land masses separated by oceans
+pub struct ContinentsGenerator {
+    pub parameters: NoiseParameters
+}
+
+impl Default for ContinentsGenerator {
+    fn default() -> Self {
+        ContinentsGenerator {parameters: NoiseParameters {octaves_num: 16, lacunarity: 2.0, persistance: 2.0, power: 1.0}}
+    }
+}
+
+impl MapGenerator for ContinentsGenerator {
+    fn name(&self) -> &str {
+        "continents"
+    }
+
+    fn generate(&self, width: usize, height: usize, wrap: WrapMode, seed: u64) -> Map<f64> {
+        self.parameters.noise_map(width, height, false, wrap, seed)
+    }
+}
+
+// Archipelago: island transform with a sharp power, many small islands around the center
+pub struct ArchipelagoGenerator {
+    pub parameters: NoiseParameters
+}
+
+impl Default for ArchipelagoGenerator {
+    fn default() -> Self {
+        ArchipelagoGenerator {parameters: NoiseParameters {octaves_num: 16, lacunarity: 2.0, persistance: 1.5, power: 1.5}}
+    }
+}
+
+impl MapGenerator for ArchipelagoGenerator {
+    fn name(&self) -> &str {
+        "archipelago"
+    }
+
+    fn generate(&self, width: usize, height: usize, wrap: WrapMode, seed: u64) -> Map<f64> {
+        self.parameters.noise_map(width, height, true, wrap, seed)
+    }
+}
+
+// Pangaea: island transform with a smooth power, one big continent surrounded by ocean
+pub struct PangaeaGenerator {
+    pub parameters: NoiseParameters
+}
+
+impl Default for PangaeaGenerator {
+    fn default() -> Self {
+        PangaeaGenerator {parameters: NoiseParameters {octaves_num: 16, lacunarity: 2.0, persistance: 2.0, power: 0.9}}
+    }
+}
+
+impl MapGenerator for PangaeaGenerator {
+    fn name(&self) -> &str {
+        "pangaea"
+    }
+
+    fn generate(&self, width: usize, height: usize, wrap: WrapMode, seed: u64) -> Map<f64> {
+        self.parameters.noise_map(width, height, true, wrap, seed)
+    }
+}
+
+// Highlands: rough diamond-square relief raised toward mountains
+#[derive(Debug, Clone, Copy)]
+pub struct HighlandsParameters {
+    // Power applied to heights (below 1 raises the relief)
+    pub power: f64
+}
+
+impl Default for HighlandsParameters {
+    fn default() -> Self {
+        HighlandsParameters {power: 0.5}
+    }
+}
+
+#[derive(Default)]
+pub struct HighlandsGenerator {
+    pub parameters: HighlandsParameters
+}
+
+impl MapGenerator for HighlandsGenerator {
+    fn name(&self) -> &str {
+        "highlands"
+    }
+
+    fn generate(&self, width: usize, height: usize, wrap: WrapMode, seed: u64) -> Map<f64> {
+        let mut height_map = diamond_square(width, height, wrap, seed);
+        height_map.map = height_map.map.iter().map(|x| x.powf(self.parameters.power)).collect();
+        height_map.normalize();
+        height_map
+    }
+}
+
+// Lakes: smooth lands where only the deepest noise holes stay under water (the lower the power, the smaller the
+// lakes)
+pub struct LakesGenerator {
+    pub parameters: NoiseParameters
+}
+
+impl Default for LakesGenerator {
+    fn default() -> Self {
+        LakesGenerator {parameters: NoiseParameters {octaves_num: 8, lacunarity: 2.0, persistance: 2.5, power: 0.5}}
+    }
+}
+
+impl MapGenerator for LakesGenerator {
+    fn name(&self) -> &str {
+        "lakes"
+    }
+
+    fn generate(&self, width: usize, height: usize, wrap: WrapMode, seed: u64) -> Map<f64> {
+        self.parameters.noise_map(width, height, false, wrap, seed)
+    }
+}
+
+/// All map generator presets with their default parameters
+pub fn map_generators() -> Vec<Box<dyn MapGenerator>> {
+    vec![
+        Box::new(ContinentsGenerator::default()),
+        Box::new(ArchipelagoGenerator::default()),
+        Box::new(PangaeaGenerator::default()),
+        Box::new(HighlandsGenerator::default()),
+        Box::new(LakesGenerator::default()),
+    ]
+}
+
+/// Find a map generator preset by name
+pub fn map_generator(name: &str) -> Option<Box<dyn MapGenerator>> {
+    map_generators().into_iter().find(|generator| generator.name() == name)
+}
+
+/// Load a height map from a grayscale image file (PNG, JPEG...), bilinearly resampled to the map
+/// dimensions and normalized, dark pixels being low and bright pixels high
+///
+/// # Arguments
+///
+/// * `path` - Path of the image file (colored images are converted to grayscale)
+/// * `width` - Width of the resulting map (in cells)
+/// * `height` - Height of the resulting map (in cells)
+/// * `wrap` - Wrap mode of the map, the image is assumed to tile along wrapped axes
+///
+pub fn height_map_from_image(path: &str, width: usize, height: usize, wrap: WrapMode) -> Result<Map<f64>, String> {
+    let image = image::open(path)
+        .map_err(|error| format!("Cannot read height map image '{}': {}", path, error))?
+        .to_luma16();
+    let (image_width, image_height) = (image.width() as usize, image.height() as usize);
+    if image_width == 0 || image_height == 0 {
+        return Err(format!("Height map image '{}' is empty", path));
+    }
+
+    // Pixel value at a signed position, repeated along wrapped axes and clamped along bounded ones
+    let pixel = |y: i64, x: i64| -> f64 {
+        let y = if wrap.wraps_vertically() {y.rem_euclid(image_height as i64)} else {y.clamp(0, image_height as i64 - 1)};
+        let x = if wrap.wraps_horizontally() {x.rem_euclid(image_width as i64)} else {x.clamp(0, image_width as i64 - 1)};
+        image.get_pixel(x as u32, y as u32)[0] as f64 / u16::MAX as f64
+    };
+
+    // Cell centers are mapped onto pixel centers
+    let mut height_map = Map::new(width, height, 0.0);
+    height_map.wrap = wrap;
+    for i in 0..height {
+        let y = (i as f64 + 0.5) * image_height as f64 / height as f64 - 0.5;
+        let (y0, v) = (y.floor() as i64, y - y.floor());
+        for j in 0..width {
+            let x = (j as f64 + 0.5) * image_width as f64 / width as f64 - 0.5;
+            let (x0, u) = (x.floor() as i64, x - x.floor());
+            height_map[(i, j)] =
+                pixel(y0, x0) * (1.0 - u) * (1.0 - v) + pixel(y0, x0 + 1) * u * (1.0 - v) +
+                pixel(y0 + 1, x0) * (1.0 - u) * v + pixel(y0 + 1, x0 + 1) * u * v;
+        }
+    }
+
+    height_map.normalize();
+    Ok(height_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_deterministic_for_a_seed() {
+        for generator in map_generators() {
+            let height_map = generator.generate(24, 16, WrapMode::None, 9);
+            assert!(height_map.map == generator.generate(24, 16, WrapMode::None, 9).map, "{}", generator.name());
+            assert!(height_map.map != generator.generate(24, 16, WrapMode::None, 10).map, "{}", generator.name());
+        }
+    }
+
+    #[test]
+    fn presets_give_maps_of_the_requested_size_and_wrap() {
+        for generator in map_generators() {
+            for wrap in [WrapMode::None, WrapMode::Horizontal, WrapMode::Full] {
+                let height_map = generator.generate(30, 17, wrap, 4);
+                assert_eq!((height_map.width, height_map.height, height_map.wrap), (30, 17, wrap), "{}", generator.name());
+                assert_eq!(height_map.map.len(), 30 * 17);
+                assert!(height_map.map.iter().all(|h| h.is_finite()), "{}", generator.name());
+            }
+        }
+    }
+
+    // Load a height map from a temporary grayscale image
+    fn load_image(name: &str, image: &image::GrayImage, width: usize, height: usize, wrap: WrapMode) -> Result<Map<f64>, String> {
+        let path = std::env::temp_dir().join(format!("strategy_game_height_{}_{}.png", name, std::process::id()));
+        image.save(&path).unwrap();
+        let height_map = height_map_from_image(path.to_str().unwrap(), width, height, wrap);
+        std::fs::remove_file(&path).unwrap();
+        height_map
+    }
+
+    #[test]
+    fn image_height_maps_are_resampled_and_normalized() {
+        // Horizontal gradient from 50 to 200
+        let image = image::GrayImage::from_fn(4, 2, |x, _| image::Luma([50 + 50 * x as u8]));
+        let height_map = load_image("gradient", &image, 4, 2, WrapMode::None).unwrap();
+        assert_eq!((height_map.width, height_map.height), (4, 2));
+        for i in 0..2usize {
+            assert_eq!(height_map[(i, 0)], 0.0);
+            assert!((height_map[(i, 1)] - 1.0 / 3.0).abs() < 1e-9);
+            assert_eq!(height_map[(i, 3)], 1.0);
+        }
+
+        // Upsampled cells are interpolated between pixels, across the edge on wrapped maps
+        let height_map = load_image("upsampled", &image, 8, 4, WrapMode::None).unwrap();
+        assert!(height_map.row(0).windows(2).all(|heights| heights[0] <= heights[1]));
+        let height_map = load_image("wrapped", &image, 8, 4, WrapMode::Horizontal).unwrap();
+        assert_eq!(height_map.wrap, WrapMode::Horizontal);
+        assert!(height_map[(0, 0)] > height_map[(0, 1)]);
+    }
+
+    #[test]
+    fn missing_height_map_image_is_an_error() {
+        let error = height_map_from_image("missing/height.png", 8, 8, WrapMode::None).err().unwrap();
+        assert!(error.contains("Cannot read height map image 'missing/height.png'"));
+    }
+
+    #[test]
+    fn presets_are_found_by_name() {
+        assert_eq!(map_generator("lakes").unwrap().name(), "lakes");
+        assert!(map_generator("moon").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+
+use crate::distance::Distance2D;
+use crate::map::WrapMode;
+use crate::path_planning::DStarLite;
+
+// Incremental planner of a recent search with cells changed since it was last used
+struct CachedPlanner {
+    planner: DStarLite,
+    changed_cells: Vec<(i32, i32)>
+}
+
+/// Path finding service keeping the planners of recent searches (one per goal) so that
+/// next searches toward the same goal only repair the previous one
+pub struct PathCache {
+    // Maximum number of planners kept
+    pub capacity: usize,
+
+    // Planners from the least to the most recently used
+    planners: VecDeque<CachedPlanner>
+}
+
+impl Default for PathCache {
+    fn default() -> Self {
+        PathCache::new(16)
+    }
+}
+
+impl PathCache {
+    pub fn new(capacity: usize) -> PathCache {
+        PathCache {
+            capacity,
+            planners: VecDeque::new()
+        }
+    }
+
+    /// Forget every search (map regenerated or resized)
+    pub fn clear(&mut self) {
+        self.planners.clear();
+    }
+
+    /// Record a cell whose passability changed (unit moved or died, building destroyed...)
+    pub fn invalidate(&mut self, cell: (usize, usize)) {
+        for cached_planner in self.planners.iter_mut() {
+            cached_planner.changed_cells.push((cell.0 as i32, cell.1 as i32));
+        }
+    }
+
+    /// Cheapest path from start to goal with the cumulated cost at each position
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Position the path starts from
+    /// * `goal` - Position the path ends on
+    /// * `map_size` - Map dimensions (height, width)
+    /// * `wrap_mode` - Map edges crossed by paths
+    /// * `cost` - Cost of moving between two neighbour positions, infinite if impassable
+    /// * `heuristic` - Consistent estimation of the cost between two positions (always the same)
+    ///
+    pub fn find_path(
+        &mut self,
+        start: (i32, i32),
+        goal: (i32, i32),
+        map_size: (i32, i32),
+        wrap_mode: WrapMode,
+        cost: &dyn Fn((i32, i32), (i32, i32)) -> f64,
+        heuristic: &dyn Distance2D
+    ) -> Option<VecDeque<(i32, i32, f64)>> {
+
+        // Reuse planner toward the same goal if any, repairing it with changed cells
+        let mut cached_planner = match self.planners.iter().position(|cached_planner| cached_planner.planner.goal == goal) {
+            Some(index) => self.planners.remove(index).unwrap(),
+            None => CachedPlanner {
+                planner: DStarLite::new(start, goal, map_size, wrap_mode, heuristic),
+                changed_cells: Vec::new()
+            }
+        };
+
+        let planner = &mut cached_planner.planner;
+        if planner.start != start {
+            planner.move_start(start, heuristic);
+        }
+        planner.update_cells(&cached_planner.changed_cells, cost, heuristic);
+        cached_planner.changed_cells.clear();
+        planner.compute_shortest_path(cost, heuristic);
+        let path = planner.path(cost);
+
+        // Keep it as the most recently used, forgetting the oldest ones
+        self.planners.push_back(cached_planner);
+        while self.planners.len() > self.capacity {
+            self.planners.pop_front();
+        }
+
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    use crate::distance::ManhattanDistance2D;
+
+    const MAP_SIZE: (i32, i32) = (6, 6);
+
+    // Unit cost toward passable cells of an open 6x6 map
+    fn edge_cost(blocked_cells: &RefCell<Vec<(i32, i32)>>) -> impl Fn((i32, i32), (i32, i32)) -> f64 + '_ {
+        move |_, to| if blocked_cells.borrow().contains(&to) {f64::INFINITY} else {1.0}
+    }
+
+    fn find_path(
+        path_cache: &mut PathCache,
+        start: (i32, i32),
+        goal: (i32, i32),
+        blocked_cells: &RefCell<Vec<(i32, i32)>>
+    ) -> Option<VecDeque<(i32, i32, f64)>> {
+        path_cache.find_path(start, goal, MAP_SIZE, WrapMode::None, &edge_cost(blocked_cells), &ManhattanDistance2D {})
+    }
+
+    #[test]
+    fn searches_toward_the_same_goal_share_a_planner() {
+        let blocked_cells = RefCell::new(Vec::new());
+        let mut path_cache = PathCache::default();
+        let path = find_path(&mut path_cache, (0, 0), (5, 5), &blocked_cells).unwrap();
+        assert_eq!(path.back().unwrap().2, 10.0);
+
+        let path = find_path(&mut path_cache, (5, 0), (5, 5), &blocked_cells).unwrap();
+        assert_eq!(path.back().unwrap().2, 5.0);
+        find_path(&mut path_cache, (0, 0), (0, 5), &blocked_cells);
+        assert_eq!(path_cache.planners.len(), 2);
+    }
+
+    #[test]
+    fn invalidated_cells_are_avoided_by_cached_planners() {
+        let blocked_cells = RefCell::new(Vec::new());
+        let mut path_cache = PathCache::default();
+        let path = find_path(&mut path_cache, (2, 0), (2, 5), &blocked_cells).unwrap();
+        assert!(path.iter().any(|&(i, j, _)| (i, j) == (2, 3)));
+
+        // Wall across the straight path, with a gap on row 0
+        for i in 1..6 {
+            blocked_cells.borrow_mut().push((i, 3));
+            path_cache.invalidate((i as usize, 3));
+        }
+        assert_eq!(path_cache.planners[0].changed_cells.len(), 5);
+
+        let path = find_path(&mut path_cache, (2, 0), (2, 5), &blocked_cells).unwrap();
+        assert!(path.iter().all(|&(i, j, _)| j != 3 || i == 0));
+        assert_eq!(path.back().unwrap().2, 9.0);
+        assert!(path_cache.planners[0].changed_cells.is_empty());
+
+        // Closing the gap leaves no path, freeing it gives the straight one back
+        blocked_cells.borrow_mut().push((0, 3));
+        path_cache.invalidate((0, 3));
+        assert!(find_path(&mut path_cache, (2, 0), (2, 5), &blocked_cells).is_none());
+        blocked_cells.borrow_mut().clear();
+        for i in 0..6 {
+            path_cache.invalidate((i as usize, 3));
+        }
+        assert_eq!(find_path(&mut path_cache, (2, 0), (2, 5), &blocked_cells).unwrap().back().unwrap().2, 5.0);
+    }
+
+    #[test]
+    fn least_recently_used_planners_are_dropped() {
+        let blocked_cells = RefCell::new(Vec::new());
+        let mut path_cache = PathCache::new(2);
+        find_path(&mut path_cache, (0, 0), (5, 5), &blocked_cells);
+        find_path(&mut path_cache, (0, 0), (5, 4), &blocked_cells);
+        find_path(&mut path_cache, (0, 1), (5, 5), &blocked_cells);
+        find_path(&mut path_cache, (0, 0), (5, 3), &blocked_cells);
+
+        let goals: Vec<(i32, i32)> = path_cache.planners.iter().map(|cached_planner| cached_planner.planner.goal).collect();
+        assert_eq!(goals, vec![(5, 5), (5, 3)]);
+
+        path_cache.clear();
+        assert!(path_cache.planners.is_empty());
+    }
+}
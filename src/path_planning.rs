@@ -2,8 +2,8 @@ use std::cmp::{Eq, PartialOrd, Ord, Ordering};
 use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 
 use crate::distance::{Distance2D};
-use crate::utils::{is_in_rect};
 use crate::constraint::{PositionConstraint};
+use crate::map::{Map, WrapMode};
 
 struct CostNode {
     position: (i32, i32),
@@ -39,7 +39,7 @@ impl PartialOrd for CostNode {
 }
 
 /// Const neighbors allowed direction for 4-connexity grid
-const NEIGHBORS_DIRECTION_4C: [(i32, i32); 4] = [
+pub const NEIGHBORS_DIRECTION_4C: [(i32, i32); 4] = [
     (-1, 0),    // NORTH
     (0, -1),    // WEST
     (1, 0),     // SOUTH
@@ -47,11 +47,11 @@ const NEIGHBORS_DIRECTION_4C: [(i32, i32); 4] = [
 ];
 
 /// Const neighbors allowed direction for 8-connexity grid
-const NEIGHBORS_DIRECTION_8C: [(i32, i32); 8] = [
+pub const NEIGHBORS_DIRECTION_8C: [(i32, i32); 8] = [
     (-1, 0),    // NORTH
     (-1, -1),   // NORTH-WEST
     (0, -1),    // WEST
-    (-1, 1),    // SOUTH-WEST
+    (1, -1),    // SOUTH-WEST
     (1, 0),     // SOUTH
     (1, 1),     // SOUTH-EAST
     (0, 1),     // EAST
@@ -64,6 +64,7 @@ const NEIGHBORS_DIRECTION_8C: [(i32, i32); 8] = [
 /// 
 /// * `position` - From which position neighbors search is made
 /// * `map_size` - Map size / dimensions used to restrict possible neighbors that go out the map scope
+/// * `wrap_mode` - Map edges across which neighbors are found on the opposite side
 /// * `allowed_directions` - Allowed directions for finding neighbors / Definition of the neighbourhood
 /// * `way_position_constraints` - Position constraints that each neighbour must respect to be taken
 /// 
@@ -77,6 +78,9 @@ pub fn neighbors(
     // Map size / dimensions
     map_size: (i32, i32),
 
+    // Map edges connected to each other
+    wrap_mode: WrapMode,
+
     // Allowed direction to find neighbors
     allowed_directions: &[(i32, i32)],
 
@@ -90,9 +94,8 @@ pub fn neighbors(
     
     // Apply neighbors generation and filtering at the same time with functional features
     allowed_directions
-        .into_iter()
-        .map(|d| (position.0 + d.0, position.1 + d.1))
-        .filter(|&x| is_in_rect(x, (0, 0, map_size.0, map_size.1), false))
+        .iter()
+        .filter_map(|d| wrap_mode.wrap_position((position.0 + d.0, position.1 + d.1), map_size))
         .filter(
             |&x| 
             ((x == goal) && (goal_position_constraints.iter().all(|gpc| gpc.respect((x.0 as usize, x.1 as usize))))) ||
@@ -122,10 +125,12 @@ pub fn reconstruct_path(
 // TODO : Implement Dijkstra instead of A* for making path finding routine faster, keep A* implementation for complex AI problem as an example of code
 
 /// A* algorithm implementation used to find shortest-path on a 2D map
+#[allow(clippy::too_many_arguments)]
 pub fn astar_2d_map(
     start: (i32, i32), 
     goal: (i32, i32), 
     map_size: (i32, i32), 
+    wrap_mode: WrapMode,
     distance: impl Distance2D, 
     heuristic: impl Distance2D,
     way_position_constraints: Vec<Box<dyn PositionConstraint>>,
@@ -165,7 +170,7 @@ pub fn astar_2d_map(
         open_set.remove(&current.position);
 
         // Visit each neighbour of current node to explore and find cheapest paths
-        for neighbour in &neighbors(current.position, goal, map_size, &NEIGHBORS_DIRECTION_4C, &way_position_constraints, &goal_position_constraints) {
+        for neighbour in &neighbors(current.position, goal, map_size, wrap_mode, &NEIGHBORS_DIRECTION_4C, &way_position_constraints, &goal_position_constraints) {
             let current_to_neighbour_distance = distance.evaluate((current_pos.0 as f64, current_pos.1 as f64), (neighbour.0 as f64, neighbour.1 as f64));
             let tentative_distance_from_start = distance_from_start[&current_pos] + current_to_neighbour_distance;
 
@@ -181,7 +186,7 @@ pub fn astar_2d_map(
                 let cost = tentative_distance_from_start + heuristic.evaluate((neighbour.0 as f64, neighbour.1 as f64), (goal.0 as f64, goal.1 as f64));
                 if !open_set.contains(neighbour) {
                     open_set.insert(*neighbour);
-                    open_priority_queue.push(CostNode{position: *neighbour, cost: cost});
+                    open_priority_queue.push(CostNode{position: *neighbour, cost});
                 }
             }
         }
@@ -189,3 +194,692 @@ pub fn astar_2d_map(
 
     None
 }
+
+/// Key of a node in D* Lite priority queue, smallest key (compared lexicographically) first
+struct KeyNode {
+    position: (i32, i32),
+    key: (f64, f64)
+}
+
+impl PartialEq for KeyNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for KeyNode {}
+
+impl Ord for KeyNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key.partial_cmp(&self.key).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for KeyNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// D* Lite incremental planner: searches backward from the goal so that the start can move along
+/// the path and edge costs can change (cells getting blocked or freed) while reusing previous search
+///
+/// Edge costs are given by a function of the two positions, infinite for impassable edges.
+/// Heuristic must be consistent with edge costs and always the same for a planner.
+pub struct DStarLite {
+    pub start: (i32, i32),
+    pub goal: (i32, i32),
+    map_size: (i32, i32),
+    wrap_mode: WrapMode,
+
+    // Cost of the cheapest known path from each node to goal, and its one-step lookahead
+    g: HashMap<(i32, i32), f64>,
+    rhs: HashMap<(i32, i32), f64>,
+
+    // Inconsistent nodes, with their current key (older keys in the queue are outdated)
+    open_priority_queue: BinaryHeap<KeyNode>,
+    open_keys: HashMap<(i32, i32), (f64, f64)>,
+
+    // Key modifier accumulated when start moves, and start position of last search
+    key_modifier: f64,
+    last_start: (i32, i32)
+}
+
+impl DStarLite {
+    pub fn new(start: (i32, i32), goal: (i32, i32), map_size: (i32, i32), wrap_mode: WrapMode, heuristic: &dyn Distance2D) -> DStarLite {
+        let mut planner = DStarLite {
+            start,
+            goal,
+            map_size,
+            wrap_mode,
+            g: HashMap::new(),
+            rhs: HashMap::new(),
+            open_priority_queue: BinaryHeap::new(),
+            open_keys: HashMap::new(),
+            key_modifier: 0.0,
+            last_start: start
+        };
+
+        planner.rhs.insert(goal, 0.0);
+        let key = planner.calculate_key(goal, heuristic);
+        planner.push(goal, key);
+
+        planner
+    }
+
+    fn g(&self, position: (i32, i32)) -> f64 {
+        *self.g.get(&position).unwrap_or(&f64::INFINITY)
+    }
+
+    fn rhs(&self, position: (i32, i32)) -> f64 {
+        *self.rhs.get(&position).unwrap_or(&f64::INFINITY)
+    }
+
+    fn neighbors(&self, position: (i32, i32)) -> Vec<(i32, i32)> {
+        NEIGHBORS_DIRECTION_4C
+            .iter()
+            .filter_map(|d| self.wrap_mode.wrap_position((position.0 + d.0, position.1 + d.1), self.map_size))
+            .collect()
+    }
+
+    fn calculate_key(&self, position: (i32, i32), heuristic: &dyn Distance2D) -> (f64, f64) {
+        let cost = self.g(position).min(self.rhs(position));
+        let h = heuristic.evaluate((self.start.0 as f64, self.start.1 as f64), (position.0 as f64, position.1 as f64));
+        (cost + h + self.key_modifier, cost)
+    }
+
+    fn push(&mut self, position: (i32, i32), key: (f64, f64)) {
+        self.open_keys.insert(position, key);
+        self.open_priority_queue.push(KeyNode {position, key});
+    }
+
+    fn update_vertex(&mut self, position: (i32, i32), cost: &dyn Fn((i32, i32), (i32, i32)) -> f64, heuristic: &dyn Distance2D) {
+        if position != self.goal {
+            let rhs = self.neighbors(position)
+                .iter()
+                .map(|successor| cost(position, *successor) + self.g(*successor))
+                .fold(f64::INFINITY, f64::min);
+            self.rhs.insert(position, rhs);
+        }
+
+        if self.g(position) != self.rhs(position) {
+            let key = self.calculate_key(position, heuristic);
+            self.push(position, key);
+        }
+        else {
+            self.open_keys.remove(&position);
+        }
+    }
+
+    /// Move start position (the unit moved along the path or a new unit plans toward the same goal)
+    pub fn move_start(&mut self, start: (i32, i32), heuristic: &dyn Distance2D) {
+        self.key_modifier += heuristic.evaluate(
+            (self.last_start.0 as f64, self.last_start.1 as f64),
+            (start.0 as f64, start.1 as f64)
+        );
+        self.last_start = start;
+        self.start = start;
+    }
+
+    /// Take into account cells whose passability changed (costs of edges entering them)
+    pub fn update_cells(&mut self, cells: &[(i32, i32)], cost: &dyn Fn((i32, i32), (i32, i32)) -> f64, heuristic: &dyn Distance2D) {
+        for cell in cells.iter() {
+            self.update_vertex(*cell, cost, heuristic);
+            for neighbour in self.neighbors(*cell) {
+                self.update_vertex(neighbour, cost, heuristic);
+            }
+        }
+    }
+
+    /// Expand inconsistent nodes until the cheapest path from start is known
+    pub fn compute_shortest_path(&mut self, cost: &dyn Fn((i32, i32), (i32, i32)) -> f64, heuristic: &dyn Distance2D) {
+        while let Some(current) = self.open_priority_queue.pop() {
+
+            // Skip outdated queue entries
+            if self.open_keys.get(&current.position) != Some(&current.key) {
+                continue;
+            }
+
+            let start_key = self.calculate_key(self.start, heuristic);
+            if current.key >= start_key && self.rhs(self.start) == self.g(self.start) {
+                self.open_priority_queue.push(current);
+                break;
+            }
+
+            let position = current.position;
+            let new_key = self.calculate_key(position, heuristic);
+            if current.key < new_key {
+                self.push(position, new_key);
+            }
+            else if self.g(position) > self.rhs(position) {
+                // Over-consistent node: its cost decreased, propagate to predecessors
+                self.g.insert(position, self.rhs(position));
+                self.open_keys.remove(&position);
+                for neighbour in self.neighbors(position) {
+                    self.update_vertex(neighbour, cost, heuristic);
+                }
+            }
+            else {
+                // Under-consistent node: its cost increased, recompute it and its predecessors
+                self.g.insert(position, f64::INFINITY);
+                self.update_vertex(position, cost, heuristic);
+                for neighbour in self.neighbors(position) {
+                    self.update_vertex(neighbour, cost, heuristic);
+                }
+            }
+        }
+    }
+
+    /// Path from start to goal with the cumulated cost at each position, None if goal is unreachable
+    pub fn path(&self, cost: &dyn Fn((i32, i32), (i32, i32)) -> f64) -> Option<VecDeque<(i32, i32, f64)>> {
+        if self.g(self.start).min(self.rhs(self.start)).is_infinite() {
+            return None;
+        }
+
+        let mut path = VecDeque::new();
+        let mut current = self.start;
+        let mut total_cost = 0.0;
+        path.push_back((current.0, current.1, total_cost));
+
+        // Follow the cheapest successors, a path never needs more steps than there are cells
+        let max_steps = (self.map_size.0 * self.map_size.1) as usize;
+        while current != self.goal {
+            if path.len() > max_steps {
+                return None;
+            }
+
+            let (next, next_cost) = self.neighbors(current)
+                .into_iter()
+                .map(|successor| (successor, cost(current, successor)))
+                .min_by(|a, b| (a.1 + self.g(a.0)).partial_cmp(&(b.1 + self.g(b.0))).unwrap_or(Ordering::Equal))?;
+            if (next_cost + self.g(next)).is_infinite() {
+                return None;
+            }
+
+            total_cost += next_cost;
+            current = next;
+            path.push_back((current.0, current.1, total_cost));
+        }
+
+        Some(path)
+    }
+}
+
+/// Bake position constraints into a passability bitmap, each constraint being evaluated once per cell
+pub fn passability_map(width: usize, height: usize, wrap_mode: WrapMode, constraints: &[Box<dyn PositionConstraint>]) -> Map<bool> {
+    let mut passable_map = Map::new(width, height, true);
+    passable_map.wrap = wrap_mode;
+    for i in 0..height {
+        for j in 0..width {
+            passable_map[(i, j)] = constraints.iter().all(|constraint| constraint.respect((i, j)));
+        }
+    }
+    passable_map
+}
+
+/// A* search on flat buffers sized to the map, reusable from one search to another
+///
+/// Buffers are never cleared: a node value is only valid if its generation is the one of the current search.
+pub struct DenseAStar {
+    width: usize,
+    height: usize,
+    generation: u32,
+
+    // Search generation in which each node was reached and closed
+    reached_generation: Vec<u32>,
+    closed_generation: Vec<u32>,
+
+    // For each node, cost of the cheapest path from start and previous node on this path
+    distance_from_start: Vec<f64>,
+    best_previous_node: Vec<usize>
+}
+
+impl DenseAStar {
+    pub fn new(width: usize, height: usize) -> DenseAStar {
+        let cells_num = width * height;
+        DenseAStar {
+            width,
+            height,
+            generation: 0,
+            reached_generation: vec![0; cells_num],
+            closed_generation: vec![0; cells_num],
+            distance_from_start: vec![f64::INFINITY; cells_num],
+            best_previous_node: vec![0; cells_num]
+        }
+    }
+
+    fn next_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+
+        // Generation counter overflowed: stale values could look valid, reset them once
+        if self.generation == 0 {
+            self.reached_generation.iter_mut().for_each(|generation| *generation = 0);
+            self.closed_generation.iter_mut().for_each(|generation| *generation = 0);
+            self.generation = 1;
+        }
+    }
+
+    /// Shortest path from start to goal with the cumulated cost at each position, None if goal is unreachable
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Position the path starts from
+    /// * `goal` - Position the path ends on
+    /// * `passable_map` - Cells a path can go through (goal included), also giving map wrap mode
+    /// * `distance` - Cost of moving between two neighbour positions
+    /// * `heuristic` - Admissible estimation of the cost between two positions
+    ///
+    pub fn search(
+        &mut self,
+        start: (i32, i32),
+        goal: (i32, i32),
+        passable_map: &Map<bool>,
+        distance: &impl Distance2D,
+        heuristic: &impl Distance2D
+    ) -> Option<VecDeque<(i32, i32, f64)>> {
+        assert!(
+            passable_map.width == self.width && passable_map.height == self.height,
+            "Passable map ({}x{}) does not match search buffers ({}x{})",
+            passable_map.width, passable_map.height, self.width, self.height
+        );
+        let width = self.width;
+        let passable = |position: (i32, i32)| passable_map.map[position.0 as usize * width + position.1 as usize];
+        self.search_with(start, goal, passable_map.wrap, passable, distance, heuristic)
+    }
+
+    /// Same search as `search`, passability being evaluated on the cells the search reaches only instead of
+    /// being read from a baked map (map dimensions are the ones of the buffers)
+    pub fn search_with(
+        &mut self,
+        start: (i32, i32),
+        goal: (i32, i32),
+        wrap_mode: WrapMode,
+        passable: impl Fn((i32, i32)) -> bool,
+        distance: &impl Distance2D,
+        heuristic: &impl Distance2D
+    ) -> Option<VecDeque<(i32, i32, f64)>> {
+        self.next_generation();
+        let generation = self.generation;
+        let width = self.width;
+        let ravel = |position: (i32, i32)| position.0 as usize * width + position.1 as usize;
+        let map_size = (self.height as i32, self.width as i32);
+        let goal_f = (goal.0 as f64, goal.1 as f64);
+
+        let start_index = ravel(start);
+        self.reached_generation[start_index] = generation;
+        self.distance_from_start[start_index] = 0.0;
+
+        // Nodes are pushed again when their cost decreases, outdated entries are skipped once closed
+        let mut open_priority_queue = BinaryHeap::new();
+        open_priority_queue.push(CostNode {position: start, cost: heuristic.evaluate((start.0 as f64, start.1 as f64), goal_f)});
+
+        while let Some(current) = open_priority_queue.pop() {
+            let current_pos = current.position;
+            let current_index = ravel(current_pos);
+            if self.closed_generation[current_index] == generation {
+                continue;
+            }
+            self.closed_generation[current_index] = generation;
+
+            if current_pos == goal {
+                return Some(self.reconstruct_path(start, goal));
+            }
+
+            let current_f = (current_pos.0 as f64, current_pos.1 as f64);
+            for d in NEIGHBORS_DIRECTION_4C.iter() {
+                let neighbour = match wrap_mode.wrap_position((current_pos.0 + d.0, current_pos.1 + d.1), map_size) {
+                    Some(neighbour) => neighbour,
+                    None => continue
+                };
+                let neighbour_index = ravel(neighbour);
+                if self.closed_generation[neighbour_index] == generation || !passable(neighbour) {
+                    continue;
+                }
+
+                let neighbour_f = (neighbour.0 as f64, neighbour.1 as f64);
+                let tentative_distance_from_start = self.distance_from_start[current_index] + distance.evaluate(current_f, neighbour_f);
+                let reached = self.reached_generation[neighbour_index] == generation;
+                if !reached || tentative_distance_from_start < self.distance_from_start[neighbour_index] {
+                    self.reached_generation[neighbour_index] = generation;
+                    self.distance_from_start[neighbour_index] = tentative_distance_from_start;
+                    self.best_previous_node[neighbour_index] = current_index;
+
+                    let cost = tentative_distance_from_start + heuristic.evaluate(neighbour_f, goal_f);
+                    open_priority_queue.push(CostNode {position: neighbour, cost});
+                }
+            }
+        }
+
+        None
+    }
+
+    fn reconstruct_path(&self, start: (i32, i32), goal: (i32, i32)) -> VecDeque<(i32, i32, f64)> {
+        let start_index = start.0 as usize * self.width + start.1 as usize;
+        let mut index = goal.0 as usize * self.width + goal.1 as usize;
+        let mut total_path = VecDeque::new();
+        loop {
+            total_path.push_front(((index / self.width) as i32, (index % self.width) as i32, self.distance_from_start[index]));
+            if index == start_index {
+                break;
+            }
+            index = self.best_previous_node[index];
+        }
+        total_path
+    }
+}
+
+/// Integration field of the cheapest cost to reach the closest goal from every cell, shared by a group of units
+/// moving toward these goals instead of each unit searching its own path
+pub struct FlowField {
+    pub goals: Vec<(i32, i32)>,
+
+    // Cost of the cheapest path from each cell to the closest goal, infinite if no goal is reachable from it
+    pub integration_map: Map<f64>
+}
+
+impl FlowField {
+    /// Integrate costs backward from the goals with a Dijkstra search over the whole map
+    ///
+    /// # Arguments
+    ///
+    /// * `goals` - Positions flows lead to, each flow to the closest one (integrated even if impassable, to attack it)
+    /// * `passable_map` - Cells a flow can go through, also giving map wrap mode
+    /// * `distance` - Cost of moving between two neighbour positions
+    ///
+    pub fn new(goals: &[(i32, i32)], passable_map: &Map<bool>, distance: &impl Distance2D) -> FlowField {
+        let mut integration_map = Map::new(passable_map.width, passable_map.height, f64::INFINITY);
+        integration_map.wrap = passable_map.wrap;
+
+        let mut open_priority_queue = BinaryHeap::new();
+        for goal in goals.iter() {
+            integration_map[(goal.0 as usize, goal.1 as usize)] = 0.0;
+            open_priority_queue.push(CostNode {position: *goal, cost: 0.0});
+        }
+
+        while let Some(current) = open_priority_queue.pop() {
+            let current_pos = current.position;
+            let current_index = (current_pos.0 as usize, current_pos.1 as usize);
+
+            // Outdated entry of a node reached again with a lower cost
+            if current.cost > integration_map[current_index] {
+                continue;
+            }
+
+            let current_f = (current_pos.0 as f64, current_pos.1 as f64);
+            for d in NEIGHBORS_DIRECTION_4C.iter() {
+                let neighbour = match passable_map.wrap_position((current_pos.0 + d.0, current_pos.1 + d.1)) {
+                    Some(neighbour) => neighbour,
+                    None => continue
+                };
+                let neighbour_index = (neighbour.0 as usize, neighbour.1 as usize);
+                if !passable_map[neighbour_index] {
+                    continue;
+                }
+
+                // Cost of moving from the neighbour toward the goal through the current node
+                let cost = distance.evaluate((neighbour.0 as f64, neighbour.1 as f64), current_f) + current.cost;
+                if cost < integration_map[neighbour_index] {
+                    integration_map[neighbour_index] = cost;
+                    open_priority_queue.push(CostNode {position: neighbour, cost});
+                }
+            }
+        }
+
+        FlowField {goals: goals.to_vec(), integration_map}
+    }
+
+    /// Cost of the cheapest path from a position to the closest goal
+    pub fn cost_to_goal(&self, position: (i32, i32)) -> f64 {
+        self.integration_map.get(position).copied().unwrap_or(f64::INFINITY)
+    }
+
+    /// Neighbours of a position that are closer to a goal with the cost of moving on them, from the
+    /// best way to a goal to the worst (the following ones are detours around a blocked best one), none if no goal
+    /// is reachable from it
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position to flow from
+    /// * `distance` - Cost of moving between two neighbour positions, the one the field was integrated with
+    ///
+    pub fn next_positions(&self, position: (i32, i32), distance: &impl Distance2D) -> Vec<((i32, i32), f64)> {
+        let cost_to_goal = self.cost_to_goal(position);
+        if cost_to_goal.is_infinite() {
+            return Vec::new();
+        }
+        let position_f = (position.0 as f64, position.1 as f64);
+
+        let mut next_positions: Vec<((i32, i32), f64)> = NEIGHBORS_DIRECTION_4C
+            .iter()
+            .filter_map(|d| self.integration_map.wrap_position((position.0 + d.0, position.1 + d.1)))
+            .filter(|neighbour| self.cost_to_goal(*neighbour) < cost_to_goal)
+            .map(|neighbour| (neighbour, distance.evaluate(position_f, (neighbour.0 as f64, neighbour.1 as f64))))
+            .collect();
+        next_positions.sort_by(|a, b| {
+            (a.1 + self.cost_to_goal(a.0)).partial_cmp(&(b.1 + self.cost_to_goal(b.0))).unwrap_or(Ordering::Equal)
+        });
+        next_positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::distance::{EuclideanDistance2D, EuclideanDistanceWHeight2D};
+
+    // Walls (#) the paths have to go around
+    const TEST_MAP: [&str; 6] = [
+        "..........",
+        ".####.....",
+        "....#..##.",
+        "..#.#...#.",
+        "..#...#.#.",
+        "..#...#...",
+    ];
+
+    fn test_passable_map() -> Map<bool> {
+        let mut passable_map = Map::new(TEST_MAP[0].len(), TEST_MAP.len(), true);
+        for (i, row) in TEST_MAP.iter().enumerate() {
+            for (j, cell) in row.chars().enumerate() {
+                passable_map[(i, j)] = cell != '#';
+            }
+        }
+        passable_map
+    }
+
+    // Uneven heights so that cheapest paths are not only the shortest ones
+    fn test_height_map() -> Map<f64> {
+        let mut height_map = Map::new(TEST_MAP[0].len(), TEST_MAP.len(), 0.0);
+        for i in 0..TEST_MAP.len() {
+            for j in 0..TEST_MAP[0].len() {
+                height_map[(i, j)] = ((i * 7 + j * 3) % 5) as f64 / 10.0;
+            }
+        }
+        height_map
+    }
+
+    struct PassableConstraint {
+        passable_map: Map<bool>
+    }
+
+    impl PositionConstraint for PassableConstraint {
+        fn respect(&self, pos: (usize, usize)) -> bool {
+            self.passable_map[pos]
+        }
+    }
+
+    // Reference cost of the cheapest path computed by A*
+    fn astar_cost(start: (i32, i32), goal: (i32, i32), passable_map: &Map<bool>, height_map: &Map<f64>) -> Option<f64> {
+        let constraints = || -> Vec<Box<dyn PositionConstraint>> {
+            vec![Box::new(PassableConstraint {passable_map: passable_map.clone()})]
+        };
+        astar_2d_map(
+            start,
+            goal,
+            (passable_map.height as i32, passable_map.width as i32),
+            WrapMode::None,
+            EuclideanDistanceWHeight2D {height_map},
+            EuclideanDistance2D {},
+            constraints(),
+            constraints()
+        ).map(|path| path.back().unwrap().2)
+    }
+
+    fn edge_cost<'m>(passable_map: &'m Map<bool>, height_map: &'m Map<f64>) -> impl Fn((i32, i32), (i32, i32)) -> f64 + 'm {
+        move |from, to| {
+            if passable_map[(to.0 as usize, to.1 as usize)] {
+                EuclideanDistanceWHeight2D {height_map}.evaluate((from.0 as f64, from.1 as f64), (to.0 as f64, to.1 as f64))
+            }
+            else {
+                f64::INFINITY
+            }
+        }
+    }
+
+    fn assert_cost_eq(cost: f64, expected: f64) {
+        assert!((cost - expected).abs() < 1e-9, "Path cost {} instead of {}", cost, expected);
+    }
+
+    fn map_size(passable_map: &Map<bool>) -> (i32, i32) {
+        (passable_map.height as i32, passable_map.width as i32)
+    }
+
+    #[test]
+    fn dstar_lite_path_cost_matches_astar() {
+        let (passable_map, height_map) = (test_passable_map(), test_height_map());
+        let cost = edge_cost(&passable_map, &height_map);
+        for (start, goal) in [((0, 0), (5, 9)), ((2, 0), (3, 7)), ((5, 5), (0, 9))] {
+            let mut planner = DStarLite::new(start, goal, map_size(&passable_map), WrapMode::None, &EuclideanDistance2D {});
+            planner.compute_shortest_path(&cost, &EuclideanDistance2D {});
+            let path = planner.path(&cost).unwrap();
+            assert_eq!((path.front().unwrap().0, path.front().unwrap().1), start);
+            assert_eq!((path.back().unwrap().0, path.back().unwrap().1), goal);
+            assert_cost_eq(path.back().unwrap().2, astar_cost(start, goal, &passable_map, &height_map).unwrap());
+        }
+    }
+
+    #[test]
+    fn dstar_lite_replans_around_blocked_cell() {
+        let (mut passable_map, height_map) = (test_passable_map(), test_height_map());
+        let (start, goal) = ((0, 0), (5, 9));
+        let heuristic = EuclideanDistance2D {};
+        let mut planner = DStarLite::new(start, goal, map_size(&passable_map), WrapMode::None, &heuristic);
+        planner.compute_shortest_path(&edge_cost(&passable_map, &height_map), &heuristic);
+        let path = planner.path(&edge_cost(&passable_map, &height_map)).unwrap();
+
+        // Block a cell in the middle of the path
+        let (i, j, _) = path[path.len() / 2];
+        passable_map[(i as usize, j as usize)] = false;
+        let cost = edge_cost(&passable_map, &height_map);
+        planner.update_cells(&[(i, j)], &cost, &heuristic);
+        planner.compute_shortest_path(&cost, &heuristic);
+        let replanned_path = planner.path(&cost).unwrap();
+
+        assert!(replanned_path.iter().all(|&(pi, pj, _)| (pi, pj) != (i, j)));
+        assert_cost_eq(replanned_path.back().unwrap().2, astar_cost(start, goal, &passable_map, &height_map).unwrap());
+    }
+
+    #[test]
+    fn dstar_lite_finds_no_path_to_enclosed_goal() {
+        let (mut passable_map, height_map) = (test_passable_map(), test_height_map());
+        passable_map[(4, 9)] = false;
+        passable_map[(5, 8)] = false;
+        let cost = edge_cost(&passable_map, &height_map);
+        let mut planner = DStarLite::new((0, 0), (5, 9), map_size(&passable_map), WrapMode::None, &EuclideanDistance2D {});
+        planner.compute_shortest_path(&cost, &EuclideanDistance2D {});
+        assert!(planner.path(&cost).is_none());
+    }
+
+    #[test]
+    fn passability_map_bakes_constraints() {
+        let passable_map = test_passable_map();
+        let constraints: Vec<Box<dyn PositionConstraint>> = vec![Box::new(PassableConstraint {passable_map: passable_map.clone()})];
+        let baked_map = passability_map(passable_map.width, passable_map.height, WrapMode::Horizontal, &constraints);
+        assert_eq!(baked_map.map, passable_map.map);
+        assert_eq!(baked_map.wrap, WrapMode::Horizontal);
+    }
+
+    #[test]
+    fn dense_astar_searches_in_a_row_match_astar() {
+        let (mut passable_map, height_map) = (test_passable_map(), test_height_map());
+        let (distance, heuristic) = (EuclideanDistanceWHeight2D {height_map: &height_map}, EuclideanDistance2D {});
+        let mut dense_astar = DenseAStar::new(passable_map.width, passable_map.height);
+
+        // Values left by a previous search must not leak into the next one
+        for (start, goal) in [((0, 0), (5, 9)), ((5, 9), (0, 0)), ((2, 0), (3, 7)), ((2, 0), (3, 7))] {
+            let path = dense_astar.search(start, goal, &passable_map, &distance, &heuristic).unwrap();
+            assert_eq!((path.front().unwrap().0, path.front().unwrap().1), start);
+            assert_eq!((path.back().unwrap().0, path.back().unwrap().1), goal);
+            assert_cost_eq(path.back().unwrap().2, astar_cost(start, goal, &passable_map, &height_map).unwrap());
+        }
+
+        // No path once goal is enclosed, then a path again once it is freed
+        passable_map[(4, 9)] = false;
+        passable_map[(5, 8)] = false;
+        assert!(dense_astar.search((0, 0), (5, 9), &passable_map, &distance, &heuristic).is_none());
+        assert!(astar_cost((0, 0), (5, 9), &passable_map, &height_map).is_none());
+
+        passable_map[(5, 8)] = true;
+        let path = dense_astar.search((0, 0), (5, 9), &passable_map, &distance, &heuristic).unwrap();
+        assert_cost_eq(path.back().unwrap().2, astar_cost((0, 0), (5, 9), &passable_map, &height_map).unwrap());
+    }
+
+    #[test]
+    fn dense_astar_survives_generation_overflow() {
+        let (passable_map, height_map) = (test_passable_map(), test_height_map());
+        let (distance, heuristic) = (EuclideanDistanceWHeight2D {height_map: &height_map}, EuclideanDistance2D {});
+        let mut dense_astar = DenseAStar::new(passable_map.width, passable_map.height);
+        dense_astar.search((0, 0), (5, 9), &passable_map, &distance, &heuristic);
+
+        dense_astar.generation = u32::MAX;
+        let path = dense_astar.search((2, 0), (3, 7), &passable_map, &distance, &heuristic).unwrap();
+        assert_eq!(dense_astar.generation, 1);
+        assert_cost_eq(path.back().unwrap().2, astar_cost((2, 0), (3, 7), &passable_map, &height_map).unwrap());
+    }
+
+    #[test]
+    fn flow_field_leads_to_goal_at_cheapest_cost() {
+        let (passable_map, height_map) = (test_passable_map(), test_height_map());
+        let distance = EuclideanDistanceWHeight2D {height_map: &height_map};
+        let goal = (5, 9);
+        let flow_field = FlowField::new(&[goal], &passable_map, &distance);
+        let mut dense_astar = DenseAStar::new(passable_map.width, passable_map.height);
+
+        for ((i, j), passable) in passable_map.iter_positions() {
+            if !passable {
+                continue;
+            }
+
+            // Follow best directions from every cell, summing move costs
+            let start = (i as i32, j as i32);
+            let (mut position, mut cost) = (start, 0.0);
+            while position != goal {
+                let next_positions = flow_field.next_positions(position, &distance);
+                assert!(!next_positions.is_empty(), "No direction from {:?}", position);
+                position = next_positions[0].0;
+                cost += next_positions[0].1;
+            }
+
+            let expected_cost = dense_astar.search(start, goal, &passable_map, &distance, &EuclideanDistance2D {}).unwrap().back().unwrap().2;
+            assert_cost_eq(flow_field.cost_to_goal(start), expected_cost);
+            assert_cost_eq(cost, expected_cost);
+        }
+    }
+
+    #[test]
+    fn unreachable_cells_have_no_flow_direction() {
+        let (mut passable_map, height_map) = (test_passable_map(), test_height_map());
+        let distance = EuclideanDistanceWHeight2D {height_map: &height_map};
+        passable_map[(4, 9)] = false;
+        passable_map[(5, 8)] = false;
+        let flow_field = FlowField::new(&[(0, 0)], &passable_map, &distance);
+
+        for position in [(5, 9), (4, 9), (1, 1)] {
+            assert!(flow_field.cost_to_goal(position).is_infinite());
+            assert!(flow_field.next_positions(position, &distance).is_empty());
+        }
+        assert!(flow_field.cost_to_goal((5, 7)).is_finite());
+        assert!(flow_field.cost_to_goal((-1, 0)).is_infinite());
+    }
+}
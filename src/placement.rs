@@ -0,0 +1,72 @@
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use crate::map::{Map, Neighbourhood};
+
+/// Passable neighbours of a cell (4-connexity), across edges on wrapped maps
+pub fn passable_neighbors(passable_map: &Map<bool>, pos: (usize, usize)) -> Vec<(usize, usize)> {
+    passable_map
+        .neighbours(pos, Neighbourhood::VonNeumann)
+        .filter(|&x| passable_map[x])
+        .collect()
+}
+
+/// Function choosing start (base) positions of players as far as possible from each other
+///
+/// # Arguments
+///
+/// * `passable_map` - Cells on which bases and units can be placed
+/// * `players_num` - Number of start positions to choose
+/// * `free_neighbors_num` - Number of passable neighbours a start position needs (to place first units)
+/// * `seed` - Seed of the first position choice (map seed, so that a map always gives the same positions)
+///
+pub fn start_positions(passable_map: &Map<bool>, players_num: usize, free_neighbors_num: usize, seed: u64) -> Vec<(usize, usize)> {
+    let map_size = (passable_map.height as f64, passable_map.width as f64);
+
+    // Candidates are passable cells with enough passable neighbours, any cell if there is none
+    let mut candidates: Vec<(usize, usize)> = passable_map
+        .iter_positions()
+        .filter(|&(pos, &passable)| passable && passable_neighbors(passable_map, pos).len() >= free_neighbors_num)
+        .map(|(pos, _)| pos)
+        .collect();
+    if candidates.is_empty() {
+        candidates = passable_map.iter_positions().map(|(pos, _)| pos).collect();
+    }
+
+    // First position is random, next ones maximize their (wrapped) distance to the closest chosen one
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut positions = vec![candidates[rng.gen_range(0..candidates.len())]];
+    let mut closest_distance: Vec<f64> = vec![f64::INFINITY; candidates.len()];
+    while positions.len() < players_num {
+        let last = positions[positions.len() - 1];
+        let mut farthest = 0;
+        for (k, candidate) in candidates.iter().enumerate() {
+            let (di, dj) = passable_map.wrap.delta(
+                (last.0 as f64, last.1 as f64),
+                (candidate.0 as f64, candidate.1 as f64),
+                map_size
+            );
+            closest_distance[k] = closest_distance[k].min((di * di + dj * dj).sqrt());
+            if closest_distance[k] > closest_distance[farthest] {
+                farthest = k;
+            }
+        }
+        positions.push(candidates[farthest]);
+    }
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_positions_depend_only_on_the_seed() {
+        let passable_map = Map::new(40, 30, true);
+        let positions = start_positions(&passable_map, 3, 4, 12);
+        assert_eq!(positions.len(), 3);
+        assert_eq!(positions, start_positions(&passable_map, 3, 4, 12));
+        assert!((0..20).any(|seed| start_positions(&passable_map, 3, 4, seed)[0] != positions[0]));
+    }
+}
@@ -14,7 +14,10 @@ pub struct Unit {
     pub damage: f64,
     pub health: f64,
     pub speed: f64,
-    pub remaining_moves: f64
+    pub remaining_moves: f64,
+
+    // Destination of a planned path kept across turns until it is reached
+    pub goal: Option<(usize, usize)>
 }
 
 impl Unit {
@@ -69,11 +72,11 @@ impl Player {
     ) -> Player {
 
         let mut player = Player {
-            num: num,
+            num,
             buildings: Vec::new(),
             units: Vec::new(),
-            principal_color: principal_color,
-            secondary_color: secondary_color
+            principal_color,
+            secondary_color
         };
 
         player.buildings.push(
@@ -89,7 +92,7 @@ impl Player {
             )
         );
 
-        return player
+        player
     }
 
     pub fn purge_dead_units(&mut self) {
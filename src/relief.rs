@@ -0,0 +1,145 @@
+use crate::map::Map;
+
+// Way terrain cells are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TerrainRendering {
+    // Flat terrain color
+    #[default]
+    Flat,
+
+    // Height color ramp shaded by the slope of the terrain, with contour lines
+    Relief
+}
+
+// Structure holding relief rendering parameters
+#[derive(Debug, Clone, Copy)]
+pub struct ReliefParameters {
+    // Direction the light comes from, clockwise from the top of the map, and its height above the horizon, in degrees
+    pub light_azimuth: f64,
+    pub light_altitude: f64,
+
+    // Multiplier of heights (between 0 and 1 on generated maps) against the cell size, a cell side counting as 1
+    pub z_factor: f64,
+
+    // How much hillshading darkens or lightens the ramp color (0: not at all, 1: fully)
+    pub strength: f64,
+
+    // Height difference between two contour lines (no contour lines if None)
+    pub contour_interval: Option<f64>,
+    pub contour_color: [f32; 4]
+}
+
+impl Default for ReliefParameters {
+    fn default() -> Self {
+        ReliefParameters {
+            light_azimuth: 315.0,
+            light_altitude: 45.0,
+            z_factor: 20.0,
+            strength: 0.8,
+            contour_interval: Some(0.1),
+            contour_color: [0.0, 0.0, 0.0, 0.35]
+        }
+    }
+}
+
+/// Compute the illumination of each cell from the normal of the height map, relative to the one of flat terrain
+/// (below 1 for slopes facing away from the light, above 1 for slopes facing it)
+///
+/// # Arguments
+///
+/// * `height_map` - Height map whose wrap mode is used for the slopes across edges
+/// * `parameters` - Light direction and height exaggeration
+pub fn hillshade_map(height_map: &Map<f64>, parameters: &ReliefParameters) -> Map<f64> {
+    let (azimuth, altitude) = (parameters.light_azimuth.to_radians(), parameters.light_altitude.to_radians());
+
+    // Light direction with j axis going right, i axis going down and z axis going up
+    let light = (
+        azimuth.sin() * altitude.cos(),
+        -azimuth.cos() * altitude.cos(),
+        altitude.sin()
+    );
+
+    let mut hillshade_map = height_map.map(|_| 1.0);
+    for ((i, j), &height) in height_map.iter_positions() {
+        // Central differences, one-sided on map edges without wrap
+        let (i, j) = (i as i32, j as i32);
+        let slope = |before: Option<&f64>, after: Option<&f64>| {
+            let samples_distance = (before.is_some() as i32 + after.is_some() as i32).max(1) as f64;
+            (after.unwrap_or(&height) - before.unwrap_or(&height)) / samples_distance
+        };
+        let dz_dj = parameters.z_factor * slope(height_map.get((i, j - 1)), height_map.get((i, j + 1)));
+        let dz_di = parameters.z_factor * slope(height_map.get((i - 1, j)), height_map.get((i + 1, j)));
+
+        let norm = (dz_dj * dz_dj + dz_di * dz_di + 1.0).sqrt();
+        let illumination = (-dz_dj * light.0 - dz_di * light.1 + light.2) / norm;
+        hillshade_map[(i as usize, j as usize)] = illumination.max(0.0) / light.2;
+    }
+
+    hillshade_map
+}
+
+/// Color of a cell of relief rendering: ramp color darkened or lightened by the cell hillshade
+pub fn shade_color(color: [f32; 4], hillshade: f64, strength: f64) -> [f32; 4] {
+    let factor = (1.0 + strength * (hillshade - 1.0)) as f32;
+    [
+        (color[0] * factor).clamp(0.0, 1.0),
+        (color[1] * factor).clamp(0.0, 1.0),
+        (color[2] * factor).clamp(0.0, 1.0),
+        color[3]
+    ]
+}
+
+/// Index of the contour interval a height lies in, contour lines being drawn between cells of different levels
+pub fn contour_level(height: f64, contour_interval: f64) -> i64 {
+    (height / contour_interval).floor() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 8x8 plane map rising by a step per cell along i and j
+    fn plane_map(step_i: f64, step_j: f64) -> Map<f64> {
+        let mut height_map = Map::new(8, 8, 0.0);
+        for i in 0..8 {
+            for j in 0..8 {
+                height_map[(i, j)] = 0.5 + step_i * i as f64 + step_j * j as f64;
+            }
+        }
+        height_map
+    }
+
+    #[test]
+    fn flat_terrain_is_uniformly_lit() {
+        let hillshade_map = hillshade_map(&Map::new(6, 4, 0.4), &ReliefParameters::default());
+        assert!(hillshade_map.map.iter().all(|hillshade| (hillshade - 1.0).abs() < 1e-9));
+        assert_eq!(shade_color([0.5, 0.2, 0.1, 1.0], 1.0, 0.8), [0.5, 0.2, 0.1, 1.0]);
+    }
+
+    #[test]
+    fn slopes_facing_the_light_are_brighter() {
+        // Light comes from the top left by default, toward which the first plane goes down
+        let parameters = ReliefParameters::default();
+        let facing_map = hillshade_map(&plane_map(0.01, 0.01), &parameters);
+        let away_map = hillshade_map(&plane_map(-0.01, -0.01), &parameters);
+        for position in [(0, 0), (4, 4), (7, 7)] {
+            assert!(facing_map[position] > 1.0);
+            assert!(away_map[position] < 1.0);
+        }
+
+        let color = [0.5, 0.5, 0.5, 1.0];
+        assert!(shade_color(color, facing_map[(4, 4)], parameters.strength)[0] > color[0]);
+        assert!(shade_color(color, away_map[(4, 4)], parameters.strength)[0] < color[0]);
+
+        // Steeper relief with a higher z factor
+        let steep_map = hillshade_map(&plane_map(0.01, 0.01), &ReliefParameters {z_factor: 40.0, ..parameters});
+        assert!(steep_map[(4, 4)] > facing_map[(4, 4)]);
+    }
+
+    #[test]
+    fn contour_levels_split_heights_by_interval() {
+        assert_eq!(contour_level(0.05, 0.1), 0);
+        assert_eq!(contour_level(0.25, 0.1), 2);
+        assert_eq!(contour_level(1.0, 0.25), 4);
+    }
+}
@@ -0,0 +1,271 @@
+use std::fs;
+use std::collections::HashMap;
+use std::rc::Weak;
+
+use serde::Deserialize;
+
+use crate::map::{Map, WrapMode};
+use crate::game::Terrain;
+
+// Source of the scenario height map
+#[derive(Debug, Clone, Deserialize)]
+pub enum MapSource {
+    // Map generator preset, with a seed to always get the same map (random if None)
+    Generator {preset: String, seed: Option<u64>},
+
+    // Hand-crafted grayscale height map image
+    Image {path: String}
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioMap {
+    pub width: usize,
+    pub height: usize,
+    #[serde(default)]
+    pub wrap: WrapMode,
+    pub source: MapSource
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ScenarioUnit {
+    pub position: (usize, usize),
+    pub damage: f64,
+    pub health: f64,
+
+    // Game default unit speed if None
+    pub speed: Option<f64>
+}
+
+impl Default for ScenarioUnit {
+    fn default() -> Self {
+        ScenarioUnit {position: (0, 0), damage: 1.0, health: 1.0, speed: None}
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ScenarioBuilding {
+    pub position: (usize, usize),
+    pub damage: f64,
+    pub health: f64
+}
+
+impl Default for ScenarioBuilding {
+    fn default() -> Self {
+        ScenarioBuilding {position: (0, 0), damage: 0.0, health: 1.0}
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScenarioPlayer {
+    // Principal color (game player colors if None)
+    pub color: Option<[f32; 4]>,
+
+    pub buildings: Vec<ScenarioBuilding>,
+    pub units: Vec<ScenarioUnit>,
+
+    // Cells owned at start in addition to the ones under buildings and units
+    pub territory: Vec<(usize, usize)>
+}
+
+// Condition ending the game with a winner, checked at the end of each turn
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum VictoryCondition {
+    // Last player having units or buildings wins
+    Elimination,
+
+    // First player owning this fraction of the passable cells wins
+    Territory(f64),
+
+    // Player owning the largest territory after this number of rounds wins
+    TurnLimit(usize)
+}
+
+// Content of a scenario file
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: String,
+    pub map: ScenarioMap,
+    pub players: Vec<ScenarioPlayer>,
+    #[serde(default)]
+    pub victory_conditions: Vec<VictoryCondition>
+}
+
+impl Scenario {
+    // Description of every unit and building position used in error messages
+    fn placements(&self) -> Vec<(String, usize, (usize, usize))> {
+        let mut placements = Vec::new();
+        for (num, player) in self.players.iter().enumerate() {
+            for (k, building) in player.buildings.iter().enumerate() {
+                placements.push((format!("Player {} building {}", num, k), num, building.position));
+            }
+            for (k, unit) in player.units.iter().enumerate() {
+                placements.push((format!("Player {} unit {}", num, k), num, unit.position));
+            }
+        }
+        placements
+    }
+
+    fn check_position(&self, what: &str, position: (usize, usize)) -> Result<(), String> {
+        if position.0 >= self.map.height || position.1 >= self.map.width {
+            return Err(format!(
+                "{} at {:?} is out of the {}x{} map",
+                what, position, self.map.width, self.map.height
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check scenario consistency that does not depend on the generated map
+    pub fn validate(&self) -> Result<(), String> {
+        if self.map.width < 3 || self.map.height < 3 {
+            return Err(format!("Map size {}x{} is too small, dimensions must be at least 3", self.map.width, self.map.height));
+        }
+        if self.players.is_empty() {
+            return Err(String::from("Scenario must have at least one player"));
+        }
+
+        // Units and buildings must be in map and never share a cell
+        let mut occupied: HashMap<(usize, usize), String> = HashMap::new();
+        for (what, _, position) in self.placements() {
+            self.check_position(&what, position)?;
+            if let Some(other) = occupied.get(&position) {
+                return Err(format!("{} at {:?} overlaps {}", what, position, other.to_lowercase()));
+            }
+            occupied.insert(position, what);
+        }
+
+        // Territory cells must be in map and not claimed by another player
+        let mut owners: HashMap<(usize, usize), usize> = HashMap::new();
+        for (_, num, position) in self.placements() {
+            owners.insert(position, num);
+        }
+        for (num, player) in self.players.iter().enumerate() {
+            for position in player.territory.iter() {
+                self.check_position(&format!("Player {} territory", num), *position)?;
+                match owners.get(position) {
+                    Some(owner) if *owner != num => {
+                        return Err(format!("Player {} territory at {:?} is already owned by player {}", num, position, owner));
+                    },
+                    _ => {
+                        owners.insert(*position, num);
+                    }
+                }
+            }
+        }
+
+        for condition in self.victory_conditions.iter() {
+            match condition {
+                VictoryCondition::Territory(fraction) if !(*fraction > 0.0 && *fraction <= 1.0) => {
+                    return Err(format!("Territory victory fraction must be in ]0, 1], found {}", fraction));
+                },
+                VictoryCondition::TurnLimit(0) => {
+                    return Err(String::from("Turn limit victory must be at least one round"));
+                },
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that no unit or building stands on impractical terrain of the generated map
+    pub fn validate_terrain(&self, terrain_map: &Map<Weak<Terrain>>) -> Result<(), String> {
+        for (what, _, position) in self.placements() {
+            if let Some(terrain) = terrain_map[position].upgrade() {
+                if terrain.impractical {
+                    return Err(format!("{} at {:?} is on impractical terrain '{}'", what, position, terrain.name));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Load and validate a RON scenario file
+pub fn load_scenario(path: &str) -> Result<Scenario, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|error| format!("Cannot read scenario file '{}': {}", path, error))?;
+    let scenario: Scenario = ron::de::from_str(&content)
+        .map_err(|error| format!("Cannot parse scenario file '{}': {}", path, error))?;
+
+    scenario.validate().map_err(|error| format!("Invalid scenario file '{}': {}", path, error))?;
+
+    Ok(scenario)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::map_generator::map_generator;
+
+    // Two players with a building and a unit each on a 10x8 map
+    fn test_scenario() -> Scenario {
+        ron::de::from_str(r#"
+            Scenario(
+                map: (width: 10, height: 8, source: Generator(preset: "pangaea", seed: Some(7))),
+                players: [
+                    (buildings: [(position: (1, 1))], units: [(position: (1, 2))], territory: [(2, 1)]),
+                    (buildings: [(position: (6, 8))], units: [(position: (6, 7))]),
+                ],
+            )
+        "#).unwrap()
+    }
+
+    #[test]
+    fn valid_scenario_passes() {
+        assert_eq!(test_scenario().validate(), Ok(()));
+    }
+
+    #[test]
+    fn out_of_map_positions_are_rejected() {
+        let mut scenario = test_scenario();
+        scenario.players[1].units[0].position = (8, 0);
+        assert!(scenario.validate().unwrap_err().contains("out of the 10x8 map"));
+
+        let mut scenario = test_scenario();
+        scenario.players[0].buildings[0].position = (0, 10);
+        assert!(scenario.validate().is_err());
+
+        let mut scenario = test_scenario();
+        scenario.players[0].territory.push((3, 12));
+        assert!(scenario.validate().unwrap_err().contains("Player 0 territory"));
+    }
+
+    #[test]
+    fn overlapping_units_and_buildings_are_rejected() {
+        let mut scenario = test_scenario();
+        scenario.players[1].units[0].position = (1, 1);
+        assert!(scenario.validate().unwrap_err().contains("overlaps player 0 building 0"));
+
+        let mut scenario = test_scenario();
+        scenario.players[0].units.push(ScenarioUnit {position: (1, 2), ..ScenarioUnit::default()});
+        assert!(scenario.validate().unwrap_err().contains("overlaps player 0 unit 0"));
+
+        let mut scenario = test_scenario();
+        scenario.players[1].territory.push((1, 2));
+        assert!(scenario.validate().unwrap_err().contains("already owned by player 0"));
+    }
+
+    #[test]
+    fn preset_and_seed_give_the_same_map() {
+        let scenario = test_scenario();
+        let (preset, seed) = match &scenario.map.source {
+            MapSource::Generator {preset, seed} => (preset.clone(), seed.unwrap()),
+            MapSource::Image {..} => panic!("Scenario map should come from a generator")
+        };
+        assert_eq!((preset.as_str(), seed), ("pangaea", 7));
+
+        let generator = map_generator(&preset).unwrap();
+        let map = generator.generate(scenario.map.width, scenario.map.height, scenario.map.wrap, seed);
+        let same_map = generator.generate(scenario.map.width, scenario.map.height, scenario.map.wrap, seed);
+        let other_map = generator.generate(scenario.map.width, scenario.map.height, scenario.map.wrap, seed + 1);
+        assert_eq!((map.width, map.height), (10, 8));
+        assert_eq!(map.map, same_map.map);
+        assert_ne!(map.map, other_map.map);
+    }
+}
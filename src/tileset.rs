@@ -0,0 +1,150 @@
+use std::fs;
+use std::path::Path;
+use std::collections::HashMap;
+
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::game::Terrain;
+
+// Rectangle of a sprite in the atlas image in pixels (x, y, width, height)
+pub type Sprite = [u32; 4];
+
+// Sprites drawn over a cell when its neighbour above, right, below or left has another terrain
+pub type TransitionSprites = [Sprite; 4];
+
+// Content of a tileset file
+#[derive(Debug, Clone, Deserialize)]
+struct TilesetFile {
+    // Atlas image path, relative to the tileset file
+    image: String,
+
+    // Unit and building sprites, tinted with player principal colors (white parts take the color)
+    #[serde(default)]
+    unit: Option<Sprite>,
+    #[serde(default)]
+    building: Option<Sprite>,
+
+    // Sprites by terrain name, and transition sprites of a terrain drawn over neighbours with a terrain before it in
+    // terrain data
+    #[serde(default)]
+    terrains: HashMap<String, Sprite>,
+    #[serde(default)]
+    transitions: HashMap<String, TransitionSprites>
+}
+
+/// Atlas image with sprites of units, buildings and terrains (primitives are drawn for the missing ones)
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    pub atlas: RgbaImage,
+    pub unit: Option<Sprite>,
+    pub building: Option<Sprite>,
+    pub terrains: HashMap<String, Sprite>,
+    pub transitions: HashMap<String, TransitionSprites>
+}
+
+impl Tileset {
+    /// Source rectangle of a sprite, as used by image drawing
+    pub fn source_rectangle(sprite: Sprite) -> [f64; 4] {
+        [sprite[0] as f64, sprite[1] as f64, sprite[2] as f64, sprite[3] as f64]
+    }
+}
+
+/// Load a tileset from a RON file giving its atlas image and the sprites in it
+///
+/// # Arguments
+///
+/// * `path` - Tileset file path
+/// * `terrains` - Terrains of terrain data, the only ones sprites can be given for
+///
+pub fn load_tileset(path: &str, terrains: &[Terrain]) -> Result<Tileset, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|error| format!("Cannot read tileset file '{}': {}", path, error))?;
+    let file: TilesetFile = ron::de::from_str(&content)
+        .map_err(|error| format!("Cannot parse tileset file '{}': {}", path, error))?;
+
+    let image_path = Path::new(path).parent().unwrap_or_else(|| Path::new("")).join(&file.image);
+    let atlas = image::open(&image_path)
+        .map_err(|error| format!("Cannot read tileset image '{}': {}", image_path.display(), error))?
+        .to_rgba8();
+
+    // Check sprites against atlas dimensions
+    let sprites = file.unit.iter()
+        .chain(file.building.iter())
+        .chain(file.terrains.values())
+        .chain(file.transitions.values().flat_map(|sprites| sprites.iter()));
+    for sprite in sprites {
+        let right = sprite[0].checked_add(sprite[2]);
+        let bottom = sprite[1].checked_add(sprite[3]);
+        if sprite[2] == 0 || sprite[3] == 0 || right.is_none_or(|right| right > atlas.width()) || bottom.is_none_or(|bottom| bottom > atlas.height()) {
+            return Err(format!(
+                "Invalid tileset file '{}': sprite {:?} is empty or out of the {}x{} atlas",
+                path, sprite, atlas.width(), atlas.height()
+            ));
+        }
+    }
+
+    // Check terrain names against terrain data, a misspelled one would never be drawn
+    let names = file.terrains.keys().map(|name| ("terrain", name))
+        .chain(file.transitions.keys().map(|name| ("transition", name)));
+    for (what, name) in names {
+        if !terrains.iter().any(|terrain| terrain.name == *name) {
+            return Err(format!("Invalid tileset file '{}': {} sprites of unknown terrain '{}'", path, what, name));
+        }
+    }
+
+    Ok(Tileset {
+        atlas,
+        unit: file.unit,
+        building: file.building,
+        terrains: file.terrains,
+        transitions: file.transitions
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::biome::load_terrain_data;
+
+    // Terrains of the shipped terrain data
+    fn terrains() -> Vec<Terrain> {
+        load_terrain_data(concat!(env!("CARGO_MANIFEST_DIR"), "/data/terrains.ron")).unwrap().terrains
+    }
+
+    // Load a tileset from a temporary file with this content, using the sample atlas image
+    fn load_content(name: &str, content: &str) -> Result<Tileset, String> {
+        let path = std::env::temp_dir().join(format!("strategy_game_tileset_{}_{}.ron", name, std::process::id()));
+        let image = concat!(env!("CARGO_MANIFEST_DIR"), "/data/tileset.png");
+        fs::write(&path, content.replace("IMAGE", image)).unwrap();
+        let tileset = load_tileset(path.to_str().unwrap(), &terrains());
+        fs::remove_file(&path).unwrap();
+        tileset
+    }
+
+    #[test]
+    fn sample_tileset_is_valid() {
+        let tileset = load_tileset(concat!(env!("CARGO_MANIFEST_DIR"), "/data/tileset.ron"), &terrains()).unwrap();
+        assert!(tileset.unit.is_some() && tileset.building.is_some());
+        assert_eq!(tileset.terrains.len(), terrains().len());
+    }
+
+    #[test]
+    fn sprites_out_of_the_atlas_are_rejected() {
+        assert!(load_content("inside", "(image: \"IMAGE\", unit: Some((240, 32, 16, 16)))").is_ok());
+        assert!(load_content("outside", "(image: \"IMAGE\", unit: Some((241, 32, 16, 16)))").unwrap_err().contains("out of"));
+        assert!(load_content("empty", "(image: \"IMAGE\", building: Some((0, 0, 0, 16)))").unwrap_err().contains("empty"));
+
+        // Coordinates overflowing with the sprite size must not wrap around into the atlas
+        let error = load_content("overflow", "(image: \"IMAGE\", terrains: {\"Sand\": (4294967295, 0, 16, 16)})").unwrap_err();
+        assert!(error.contains("out of"));
+    }
+
+    #[test]
+    fn unknown_terrains_are_rejected() {
+        let error = load_content("terrain", "(image: \"IMAGE\", terrains: {\"Sandd\": (0, 0, 16, 16)})").unwrap_err();
+        assert!(error.contains("terrain sprites of unknown terrain 'Sandd'"));
+        let error = load_content("transition", "(image: \"IMAGE\", transitions: {\"Lava\": ((0, 0, 16, 16), (0, 0, 16, 16), (0, 0, 16, 16), (0, 0, 16, 16))})").unwrap_err();
+        assert!(error.contains("transition sprites of unknown terrain 'Lava'"));
+    }
+}
@@ -17,5 +17,5 @@ pub fn is_in_rect<T: PartialOrd>(
         if y >= y1 {return false;}
     }
     
-    return true;
+    true
 }
\ No newline at end of file